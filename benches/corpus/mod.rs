@@ -0,0 +1,171 @@
+// generated lists of paths for the benchmarks, shaped like real trees but
+// made from a fixed seed so that every run, on every machine, searches
+// exactly the same paths
+
+#![allow(dead_code)]
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Corpus {
+    // deep directories of c sources, headers and build files
+    Kernel,
+    // packages nested in packages, with lots of repeated file names
+    NodeModules,
+    // a shallow mix of everything
+    Mixed
+}
+
+impl Corpus {
+    pub fn all() -> Vec<Corpus> {
+        vec![Corpus::Kernel, Corpus::NodeModules, Corpus::Mixed]
+    }
+
+    pub fn parse(s: &str) -> Option<Corpus> {
+        match s {
+            "kernel" => Some(Corpus::Kernel),
+            "node_modules" => Some(Corpus::NodeModules),
+            "mixed" => Some(Corpus::Mixed),
+            _ => None
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Corpus::Kernel => "kernel",
+            Corpus::NodeModules => "node_modules",
+            Corpus::Mixed => "mixed"
+        }
+    }
+
+    pub fn paths(&self, count: usize) -> Vec<String> {
+        let mut random = Random(42);
+
+        (0..count).map(|i| match *self {
+            Corpus::Kernel => kernel_path(&mut random, i),
+            Corpus::NodeModules => node_modules_path(&mut random, i),
+            Corpus::Mixed => mixed_path(&mut random, i)
+        }).collect()
+    }
+
+    // what people tend to type when looking for something in this sort of
+    // tree, a `<` is a backspace
+    pub fn queries(&self) -> &'static [&'static str] {
+        match *self {
+            Corpus::Kernel => &["s", "drivers/net", "kmalloc", "sched core", "Makefile", "schde<<<edule"],
+            Corpus::NodeModules => &["i", "index.js", "lodash", "react dom", "package.json", "ractt<<ct"],
+            Corpus::Mixed => &["s", "srcmain", "sdriv", "lib/util.rs", "main !test"]
+        }
+    }
+}
+
+// a linear congruential generator, good enough for picking names
+pub struct Random(pub u64);
+
+impl Random {
+    pub fn next(&mut self, n: usize) -> usize {
+        self.0 = self.0.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        ((self.0 >> 33) as usize) % n
+    }
+
+    pub fn pick<'a>(&mut self, choices: &[&'a str]) -> &'a str {
+        choices[self.next(choices.len())]
+    }
+}
+
+static KERNEL_TOP: &'static [&'static str] = &[
+    "arch", "drivers", "fs", "net", "include", "kernel", "mm", "sound", "tools", "Documentation"
+];
+
+static KERNEL_DIRECTORIES: &'static [&'static str] = &[
+    "x86", "arm64", "net", "gpu", "usb", "scsi", "ext4", "btrfs", "ipv4", "ipv6", "sched",
+    "core", "platform", "linux", "uapi", "media", "pci", "acpi", "bpf", "crypto"
+];
+
+static KERNEL_NAMES: &'static [&'static str] = &[
+    "main", "core", "init", "kmalloc", "sched", "schedule", "irq", "dma", "module", "super",
+    "inode", "socket", "tcp_input", "page_alloc", "driver", "probe", "trace", "sysfs"
+];
+
+fn kernel_path(random: &mut Random, i: usize) -> String {
+    let mut path = String::from(random.pick(KERNEL_TOP));
+
+    for _ in 0..1 + random.next(4) {
+        path.push('/');
+        path.push_str(random.pick(KERNEL_DIRECTORIES));
+    }
+
+    path.push('/');
+
+    match random.next(10) {
+        0 => path.push_str("Makefile"),
+        1 => path.push_str("Kconfig"),
+        n => {
+            path.push_str(random.pick(KERNEL_NAMES));
+            path.push_str(&format!("_{}", i % 500));
+            path.push_str(if n < 7 { ".c" } else { ".h" });
+        }
+    }
+
+    path
+}
+
+static PACKAGES: &'static [&'static str] = &[
+    "lodash", "react", "react-dom", "express", "debug", "ms", "chalk", "@babel/core",
+    "@types/node", "webpack", "typescript", "rxjs", "semver", "glob", "minimatch", "yargs"
+];
+
+static PACKAGE_DIRECTORIES: &'static [&'static str] = &["lib", "dist", "src", "es", "cjs", "umd", "types"];
+
+static PACKAGE_FILES: &'static [&'static str] = &[
+    "index.js", "index.d.ts", "package.json", "README.md", "LICENSE", "utils.js", "map.js",
+    "debounce.js", "server.js", "index.mjs", "types.d.ts", "CHANGELOG.md"
+];
+
+fn node_modules_path(random: &mut Random, i: usize) -> String {
+    let mut path = String::new();
+
+    // dependencies of dependencies
+    for _ in 0..1 + random.next(3) {
+        path.push_str("node_modules/");
+        path.push_str(random.pick(PACKAGES));
+        path.push('/');
+    }
+
+    for _ in 0..random.next(3) {
+        path.push_str(random.pick(PACKAGE_DIRECTORIES));
+        path.push('/');
+    }
+
+    // the same file names turn up in every package, the numbers keep
+    // them apart
+    if random.next(3) == 0 {
+        path.push_str(&format!("v{}/", i % 100));
+    }
+
+    path.push_str(random.pick(PACKAGE_FILES));
+    path
+}
+
+static MIXED_DIRECTORIES: &'static [&'static str] = &[
+    "src", "lib", "drivers", "tests", "include", "scripts", "docs", "net", "fs", "mm"
+];
+
+static MIXED_NAMES: &'static [&'static str] = &[
+    "main", "util", "mod", "config", "parser", "server", "client", "buffer", "state", "index"
+];
+
+static MIXED_EXTENSIONS: &'static [&'static str] = &["rs", "c", "h", "js", "md"];
+
+fn mixed_path(random: &mut Random, i: usize) -> String {
+    let mut path = String::new();
+
+    for _ in 0..1 + random.next(5) {
+        path.push_str(random.pick(MIXED_DIRECTORIES));
+        path.push('/');
+    }
+
+    path.push_str(random.pick(MIXED_NAMES));
+    path.push_str(&(i % 1000).to_string());
+    path.push('.');
+    path.push_str(random.pick(MIXED_EXTENSIONS));
+    path
+}
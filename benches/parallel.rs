@@ -0,0 +1,61 @@
+// times each keystroke of a few queries against a big generated index, on
+// a single thread and then on one for each processor
+//
+//     cargo bench --bench parallel [paths]
+//
+// an index holds on to about 56KB a path (see the suite bench), so the
+// default of 20000 paths needs a little over 1.1GB and every 100000 more
+// needs another 5.5GB or so
+
+extern crate ff;
+
+mod corpus;
+
+use std::env;
+use std::time::{Duration, Instant};
+
+use ff::index::Index;
+
+use corpus::Corpus;
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs() as f64 * 1000.0 + duration.subsec_nanos() as f64 / 1_000_000.0
+}
+
+fn main() {
+    let count = env::args().nth(1).and_then(|arg| arg.parse().ok()).unwrap_or(20_000);
+
+    let start = Instant::now();
+    let mut index = Index::new();
+
+    for path in Corpus::Mixed.paths(count) {
+        index.push(&path);
+    }
+
+    println!("indexed {} paths in {:.0}ms", count, millis(start.elapsed()));
+
+    for &(name, threads) in &[("1 thread", 1), ("all threads", 0)] {
+        println!("\n{}", name);
+
+        for query_string in Corpus::Mixed.queries() {
+            let mut query = index.query();
+            query.set_threads(threads);
+
+            let mut slowest = 0.0f64;
+            let start = Instant::now();
+
+            for c in query_string.chars() {
+                let keystroke = Instant::now();
+                query.advance(c);
+                slowest = slowest.max(millis(keystroke.elapsed()));
+            }
+
+            let advanced = millis(start.elapsed());
+            let results = Instant::now();
+            let matches = query.results().len();
+
+            println!("  {:<14} advance {:>8.1}ms (slowest key {:>7.1}ms)  results {:>8.1}ms  {:>7} matches",
+                query_string, advanced, slowest, millis(results.elapsed()), matches);
+        }
+    }
+}
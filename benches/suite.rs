@@ -0,0 +1,180 @@
+// how long it takes to build an index of each generated corpus, how much
+// memory the index holds on to, and how long each keystroke of some typical
+// queries takes, the advance along with working out the rows to show
+//
+//     cargo bench --bench suite -- [--corpus kernel|node_modules|mixed]...
+//         [--sizes 1000,10000,100000] [--dump]
+//
+// an index takes about 56KB a path, so the default sizes of 1000 and 10000
+// need around 540MB, 100000 needs around 5.5GB and anything bigger needs
+// more memory than most machines have
+//
+// with `--dump` the paths of each corpus are printed instead, so the same
+// list can be fed to something else
+
+extern crate ff;
+
+mod corpus;
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::env;
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use ff::index::Index;
+use ff::search::Outcome;
+
+use corpus::Corpus;
+
+// about as many rows as a terminal has room for
+static ROWS: usize = 50;
+
+// every allocation is counted, so that the size of an index is exact rather
+// than whatever the allocator happened to ask the system for
+struct Counting;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATED.fetch_add(layout.size(), Ordering::SeqCst);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        ALLOCATED.fetch_sub(layout.size(), Ordering::SeqCst);
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, size: usize) -> *mut u8 {
+        ALLOCATED.fetch_add(size, Ordering::SeqCst);
+        ALLOCATED.fetch_sub(layout.size(), Ordering::SeqCst);
+        System.realloc(ptr, layout, size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+struct Options {
+    corpora: Vec<Corpus>,
+    sizes: Vec<usize>,
+    dump: bool
+}
+
+impl Options {
+    fn from_args() -> Options {
+        let mut options = Options { corpora: Vec::new(), sizes: vec![1_000, 10_000], dump: false };
+        let mut args = env::args().skip(1);
+
+        while let Some(arg) = args.next() {
+            match arg.as_ref() {
+                "--corpus" => match args.next().as_ref().and_then(|name| Corpus::parse(name)) {
+                    Some(corpus) => options.corpora.push(corpus),
+                    None => usage()
+                },
+                "--sizes" => match args.next().map(|sizes| sizes.split(',').map(|size| size.parse()).collect()) {
+                    Some(Ok(sizes)) => options.sizes = sizes,
+                    _ => usage()
+                },
+                "--dump" => options.dump = true,
+                // cargo passes this on to every bench
+                "--bench" => (),
+                _ => usage()
+            }
+        }
+
+        if options.corpora.is_empty() {
+            options.corpora = Corpus::all();
+        }
+
+        options
+    }
+}
+
+fn usage() -> ! {
+    writeln!(io::stderr(), "usage: suite [--corpus kernel|node_modules|mixed]... [--sizes 10000,100000] [--dump]").ok();
+    std::process::exit(2);
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs() as f64 * 1000.0 + duration.subsec_nanos() as f64 / 1_000_000.0
+}
+
+// the time for each keystroke, a `<` goes back a character
+fn keystrokes(index: &Index, query_string: &str) -> Vec<f64> {
+    let mut query = index.query();
+
+    query_string.chars().map(|c| {
+        let start = Instant::now();
+
+        match c {
+            '<' => query.back(),
+            c => query.advance(c)
+        }
+
+        Outcome::of(&query, ROWS, 0);
+        millis(start.elapsed())
+    }).collect()
+}
+
+fn median(times: &[f64]) -> f64 {
+    let mut sorted = times.to_vec();
+    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    sorted[sorted.len() / 2]
+}
+
+fn slowest(times: &[f64]) -> f64 {
+    times.iter().cloned().fold(0.0, f64::max)
+}
+
+fn run(corpus: Corpus, size: usize) {
+    let paths = corpus.paths(size);
+
+    let before = ALLOCATED.load(Ordering::SeqCst);
+    let start = Instant::now();
+    let mut index = Index::new();
+
+    for path in &paths {
+        index.push(path);
+    }
+
+    let built = millis(start.elapsed());
+    let bytes = ALLOCATED.load(Ordering::SeqCst).saturating_sub(before);
+
+    println!("{} {}: built in {:.0}ms, {:.1}MB ({} bytes a path)",
+        corpus.name(), size, built, bytes as f64 / (1024.0 * 1024.0), bytes / size.max(1));
+
+    let mut all = Vec::new();
+
+    for query_string in corpus.queries() {
+        let times = keystrokes(&index, query_string);
+
+        println!("  {:<16} median {:>8.2}ms  slowest {:>8.2}ms", query_string, median(&times), slowest(&times));
+        all.extend(times);
+    }
+
+    println!("  {:<16} median {:>8.2}ms  slowest {:>8.2}ms", "every key", median(&all), slowest(&all));
+}
+
+fn main() {
+    let options = Options::from_args();
+
+    for &corpus in &options.corpora {
+        for &size in &options.sizes {
+            if options.dump {
+                let stdout = io::stdout();
+                let mut stdout = stdout.lock();
+
+                for path in corpus.paths(size) {
+                    writeln!(stdout, "{}", path).ok();
+                }
+
+                continue;
+            }
+
+            run(corpus, size);
+        }
+    }
+}
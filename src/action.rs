@@ -0,0 +1,199 @@
+use termion::event::Key;
+
+use shell;
+
+// what should happen when a bound key is pressed. Executed commands run
+// with the terminal handed over to them and return to the picker when they
+// finish, a command that ff becomes replaces it entirely. Reloading runs the
+// source command again for a fresh list of candidates
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Action {
+    Accept,
+    Abort,
+    Reload,
+    Execute(String),
+    Become(String)
+}
+
+impl Action {
+    // `accept`, `abort`, `reload`, `execute(cmd)` or `become(cmd)`
+    pub fn parse(s: &str) -> Option<Action> {
+        match s {
+            "accept" => return Some(Action::Accept),
+            "abort" => return Some(Action::Abort),
+            "reload" => return Some(Action::Reload),
+            _ => ()
+        }
+
+        if let Some(command) = argument(s, "execute") {
+            return Some(Action::Execute(command));
+        }
+
+        if let Some(command) = argument(s, "become") {
+            return Some(Action::Become(command));
+        }
+
+        None
+    }
+
+    // the command line to run for the marked paths, or the selected one when
+    // nothing is marked, with each quoted so that it is passed as a single
+    // argument whatever it contains
+    pub fn command(&self, paths: &[String]) -> Option<String> {
+        if paths.is_empty() {
+            return None;
+        }
+
+        match *self {
+            Action::Execute(ref template) | Action::Become(ref template) => Some(shell::substitute_all(template, paths)),
+            _ => None
+        }
+    }
+}
+
+fn argument(s: &str, name: &str) -> Option<String> {
+    if s.starts_with(name) && s[name.len()..].starts_with('(') && s.ends_with(')') {
+        Some(s[name.len() + 1..s.len() - 1].to_owned())
+    } else {
+        None
+    }
+}
+
+// `enter`, `esc`, `tab`, `ctrl-x`, `alt-x`, `f1`, a handful of named keys,
+// or a single character
+pub fn parse_key(s: &str) -> Option<Key> {
+    let key = match s {
+        "enter" => Key::Char('\n'),
+        "tab" => Key::Char('\t'),
+        "space" => Key::Char(' '),
+        "esc" => Key::Esc,
+        "bspace" => Key::Backspace,
+        "del" => Key::Delete,
+        "up" => Key::Up,
+        "down" => Key::Down,
+        "left" => Key::Left,
+        "right" => Key::Right,
+        "home" => Key::Home,
+        "end" => Key::End,
+        "pgup" => Key::PageUp,
+        "pgdn" => Key::PageDown,
+        _ => return parse_modified_key(s)
+    };
+
+    Some(key)
+}
+
+fn parse_modified_key(s: &str) -> Option<Key> {
+    let single = |s: &str| {
+        let mut chars = s.chars();
+
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => Some(c),
+            _ => None
+        }
+    };
+
+    if s.starts_with("ctrl-") {
+        single(&s["ctrl-".len()..]).map(Key::Ctrl)
+    } else if s.starts_with("alt-") {
+        single(&s["alt-".len()..]).map(Key::Alt)
+    } else if s.len() > 1 && s.starts_with('f') {
+        s[1..].parse().ok().filter(|&n| n >= 1 && n <= 12).map(Key::F)
+    } else {
+        single(s).map(Key::Char)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Bindings {
+    bindings: Vec<(Key, Action)>
+}
+
+impl Bindings {
+    pub fn new() -> Bindings {
+        Bindings::default()
+    }
+
+    // `key:action`, a later binding for the same key replaces the earlier one
+    pub fn parse(&mut self, s: &str) -> Option<()> {
+        let colon = match s.find(':') {
+            Some(colon) => colon,
+            None => return None
+        };
+
+        match (parse_key(&s[..colon]), Action::parse(&s[colon + 1..])) {
+            (Some(key), Some(action)) => {
+                self.bind(key, action);
+                Some(())
+            },
+            _ => None
+        }
+    }
+
+    pub fn bind(&mut self, key: Key, action: Action) {
+        self.bindings.retain(|&(bound, _)| bound != key);
+        self.bindings.push((key, action));
+    }
+
+    pub fn get(&self, key: &Key) -> Option<&Action> {
+        self.bindings.iter()
+            .find(|&&(ref bound, _)| bound == key)
+            .map(|&(_, ref action)| action)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_actions() {
+        assert_eq!(Some(Action::Accept), Action::parse("accept"));
+        assert_eq!(Some(Action::Reload), Action::parse("reload"));
+        assert_eq!(Some(Action::Execute(String::from("vim {}"))), Action::parse("execute(vim {})"));
+        assert_eq!(Some(Action::Become(String::from("git diff {}"))), Action::parse("become(git diff {})"));
+        assert_eq!(None, Action::parse("execute(vim {}"));
+        assert_eq!(None, Action::parse("explode"));
+    }
+
+    #[test]
+    fn parse_keys() {
+        assert_eq!(Some(Key::Char('\n')), parse_key("enter"));
+        assert_eq!(Some(Key::Ctrl('o')), parse_key("ctrl-o"));
+        assert_eq!(Some(Key::Alt('x')), parse_key("alt-x"));
+        assert_eq!(Some(Key::F(5)), parse_key("f5"));
+        assert_eq!(Some(Key::Char('f')), parse_key("f"));
+        assert_eq!(None, parse_key("f13"));
+        assert_eq!(None, parse_key("ctrl-ab"));
+    }
+
+    #[test]
+    fn bindings() {
+        let mut bindings = Bindings::new();
+
+        assert!(bindings.parse("enter:execute(vim {})").is_some());
+        assert!(bindings.parse("ctrl-d:become(git diff {})").is_some());
+        assert!(bindings.parse("enter:execute(less {})").is_some());
+        assert!(bindings.parse("enter").is_none());
+
+        assert_eq!(Some(&Action::Execute(String::from("less {}"))), bindings.get(&Key::Char('\n')));
+        assert_eq!(None, bindings.get(&Key::Ctrl('x')));
+    }
+
+    #[test]
+    fn commands_quote_the_path() {
+        let action = Action::Execute(String::from("vim {}"));
+
+        assert_eq!(Some(String::from("vim 'a b; rm c'")), action.command(&[String::from("a b; rm c")]));
+        assert_eq!(None, Action::Accept.command(&[String::from("a")]));
+        assert_eq!(None, action.command(&[]));
+    }
+
+    #[test]
+    fn commands_take_every_marked_path() {
+        let action = Action::Become(String::from("vim -o {}"));
+        let marked = vec![String::from("src/main.rs"), String::from("it's.txt")];
+
+        assert_eq!(Some(String::from("vim -o 'src/main.rs' 'it'\\''s.txt'")), action.command(&marked));
+    }
+}
@@ -0,0 +1,216 @@
+// a range of fields, counted from one, negative indexes count back from the
+// last field. A missing start or end means the first or last field
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FieldRange {
+    pub start: Option<isize>,
+    pub end: Option<isize>
+}
+
+impl FieldRange {
+    // `2`, `-1`, `2..`, `..3` or `2..-1`
+    pub fn parse(s: &str) -> Option<FieldRange> {
+        let (start, end) = match s.find("..") {
+            Some(i) => (&s[..i], &s[i + 2..]),
+            None => (s, s)
+        };
+
+        let start = match parse_index(start) {
+            Ok(start) => start,
+            Err(_) => return None
+        };
+
+        let end = match parse_index(end) {
+            Ok(end) => end,
+            Err(_) => return None
+        };
+
+        Some(FieldRange { start: start, end: end })
+    }
+
+    // the zero based, inclusive, indexes of the selected fields
+    fn resolve(&self, count: usize) -> Option<(usize, usize)> {
+        let count = count as isize;
+
+        let resolve_index = |index: isize| if index < 0 { count + index } else { index - 1 };
+
+        let start = self.start.map(&resolve_index).unwrap_or(0).max(0);
+        let end = self.end.map(&resolve_index).unwrap_or(count - 1).min(count - 1);
+
+        if start > end {
+            None
+        } else {
+            Some((start as usize, end as usize))
+        }
+    }
+}
+
+fn parse_index(s: &str) -> Result<Option<isize>, ()> {
+    if s.is_empty() {
+        return Ok(None);
+    }
+
+    match s.parse::<isize>() {
+        Ok(0) | Err(_) => Err(()),
+        Ok(index) => Ok(Some(index))
+    }
+}
+
+// a comma separated list of field ranges
+pub fn parse_ranges(s: &str) -> Option<Vec<FieldRange>> {
+    s.split(',').map(FieldRange::parse).collect()
+}
+
+// how to split lines into fields, which of them should be matched against
+// and which of them should be shown. With no ranges the whole line is used
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Fields {
+    pub delimiter: Option<String>,
+    pub nth: Vec<FieldRange>,
+    pub with_nth: Vec<FieldRange>
+}
+
+impl Fields {
+    // the text to match against, along with the position in the line of
+    // each of its bytes, or nothing if the whole line should be matched
+    pub fn key(&self, line: &str) -> Option<(String, Vec<usize>)> {
+        if self.nth.is_empty() {
+            return None;
+        }
+
+        let mut key = String::new();
+        let mut offsets = Vec::new();
+
+        for (i, &(start, end)) in self.select(line, &self.nth).iter().enumerate() {
+            if i > 0 {
+                key.push(' ');
+                offsets.push(start);
+            }
+
+            key.push_str(&line[start..end]);
+            offsets.extend(start..end);
+        }
+
+        Some((key, offsets))
+    }
+
+    // the byte ranges of the line to show, empty if all of it should be
+    pub fn display(&self, line: &str) -> Vec<(usize, usize)> {
+        if self.with_nth.is_empty() {
+            return Vec::new();
+        }
+
+        self.select(line, &self.with_nth)
+    }
+
+    // each range of fields becomes a single span of the line, so the
+    // delimiters between the fields within it are kept
+    fn select(&self, line: &str, ranges: &[FieldRange]) -> Vec<(usize, usize)> {
+        let fields = self.split(line);
+
+        ranges.iter()
+            .filter_map(|range| range.resolve(fields.len()))
+            .map(|(start, end)| (fields[start].0, fields[end].1))
+            .collect()
+    }
+
+    // without a delimiter fields are separated by any amount of whitespace
+    fn split(&self, line: &str) -> Vec<(usize, usize)> {
+        let mut fields = Vec::new();
+
+        match self.delimiter {
+            Some(ref delimiter) if !delimiter.is_empty() => {
+                let mut start = 0;
+
+                for (index, _) in line.match_indices(delimiter.as_str()) {
+                    fields.push((start, index));
+                    start = index + delimiter.len();
+                }
+
+                fields.push((start, line.len()));
+            },
+
+            _ => {
+                let mut start: Option<usize> = None;
+
+                for (index, c) in line.char_indices() {
+                    match (start, c.is_whitespace()) {
+                        (None, false) => start = Some(index),
+                        (Some(field_start), true) => {
+                            fields.push((field_start, index));
+                            start = None;
+                        },
+                        _ => ()
+                    }
+                }
+
+                start.map(|field_start| fields.push((field_start, line.len())));
+            }
+        }
+
+        fields
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fields(delimiter: Option<&str>, nth: &str, with_nth: &str) -> Fields {
+        Fields {
+            delimiter: delimiter.map(|delimiter| delimiter.to_owned()),
+            nth: if nth.is_empty() { Vec::new() } else { parse_ranges(nth).unwrap() },
+            with_nth: if with_nth.is_empty() { Vec::new() } else { parse_ranges(with_nth).unwrap() }
+        }
+    }
+
+    #[test]
+    fn parse_field_ranges() {
+        assert_eq!(Some(FieldRange { start: Some(2), end: Some(2) }), FieldRange::parse("2"));
+        assert_eq!(Some(FieldRange { start: Some(-1), end: Some(-1) }), FieldRange::parse("-1"));
+        assert_eq!(Some(FieldRange { start: Some(2), end: None }), FieldRange::parse("2.."));
+        assert_eq!(Some(FieldRange { start: None, end: Some(3) }), FieldRange::parse("..3"));
+        assert_eq!(None, FieldRange::parse("0"));
+        assert_eq!(None, FieldRange::parse("a"));
+        assert_eq!(2, parse_ranges("1,3..").unwrap().len());
+    }
+
+    #[test]
+    fn whole_line() {
+        let fields = Fields::default();
+
+        assert_eq!(None, fields.key("a b c"));
+        assert_eq!(Vec::<(usize, usize)>::new(), fields.display("a b c"));
+    }
+
+    #[test]
+    fn whitespace_fields() {
+        let fields = fields(None, "2", "2..");
+        let line = "1234  src/main.rs:42: fn main";
+
+        let (key, offsets) = fields.key(line).unwrap();
+
+        assert_eq!("src/main.rs:42:", key);
+        assert_eq!(6, offsets[0]);
+        assert_eq!(vec![(6, line.len())], fields.display(line));
+    }
+
+    #[test]
+    fn delimited_fields() {
+        let fields = fields(Some(":"), "1,-1", "");
+        let line = "src/main.rs:42: fn main";
+
+        let (key, offsets) = fields.key(line).unwrap();
+
+        assert_eq!("src/main.rs  fn main", key);
+        assert_eq!(10, offsets[10]);
+        assert_eq!(15, offsets[11]);
+        assert_eq!(15, offsets[12]);
+    }
+
+    #[test]
+    fn out_of_range_fields() {
+        let fields = fields(Some(":"), "5", "");
+
+        assert_eq!(Some((String::new(), Vec::new())), fields.key("a:b"));
+    }
+}
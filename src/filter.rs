@@ -0,0 +1,220 @@
+use regex::{self, Regex, RegexBuilder};
+
+use query::Match;
+
+// the non fuzzy ways of matching a query against the paths in an index, both
+// end up as a regular expression over the original path
+pub enum Filter {
+    Regex(Regex),
+    Glob(Regex)
+}
+
+impl Filter {
+    pub fn regex(pattern: &str, case_insensitive: bool) -> Option<Filter> {
+        build(pattern, case_insensitive).map(Filter::Regex)
+    }
+
+    pub fn glob(pattern: &str, case_insensitive: bool) -> Option<Filter> {
+        build(&glob_to_regex(pattern), case_insensitive).map(Filter::Glob)
+    }
+
+    pub fn matches<'a, I: Iterator<Item = &'a String>>(&self, paths: I) -> Vec<Match> {
+        paths
+            .filter_map(|path| {
+                self.positions(path).map(|positions| {
+                    Match {
+                        path: path.to_owned(),
+                        score: path.len(),
+                        positions: positions
+                    }
+                })
+            })
+            .collect()
+    }
+
+    // regular expressions can match anywhere, and more than once, every
+    // matched span is highlighted, for globs only the literal parts are
+    fn positions(&self, path: &str) -> Option<Vec<usize>> {
+        let mut positions = Vec::new();
+
+        match *self {
+            Filter::Regex(ref regex) => {
+                if !regex.is_match(path) {
+                    return None;
+                }
+
+                for m in regex.find_iter(path) {
+                    positions.extend(char_positions(path, m.start(), m.end()));
+                }
+            },
+
+            Filter::Glob(ref regex) => {
+                let captures = match regex.captures(path) {
+                    Some(captures) => captures,
+                    None => return None
+                };
+
+                for m in (1..captures.len()).filter_map(|i| captures.get(i)) {
+                    positions.extend(char_positions(path, m.start(), m.end()));
+                }
+            }
+        }
+
+        Some(positions)
+    }
+}
+
+fn build(pattern: &str, case_insensitive: bool) -> Option<Regex> {
+    RegexBuilder::new(pattern)
+        .case_insensitive(case_insensitive)
+        .build()
+        .ok()
+}
+
+fn char_positions(path: &str, start: usize, end: usize) -> Vec<usize> {
+    path[start..end].char_indices().map(|(i, _)| start + i).collect()
+}
+
+// globs have to match the whole path, unless they don't contain a `/` in
+// which case, like gitignore, they only have to match the end of it. Runs of
+// literal characters are captured so that they can be highlighted
+fn glob_to_regex(glob: &str) -> String {
+    let mut pattern = String::from("^");
+    let mut literal = String::new();
+    let mut chars = glob.chars().peekable();
+
+    if !glob.contains('/') {
+        pattern.push_str("(?:.*/)?");
+    }
+
+    while let Some(c) = chars.next() {
+        match c {
+            '*' | '?' | '[' => push_literal(&mut pattern, &mut literal),
+            '\\' => {
+                chars.next().map(|escaped| literal.push(escaped));
+                continue;
+            },
+            _ => {
+                literal.push(c);
+                continue;
+            }
+        }
+
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+
+                if chars.peek() == Some(&'/') {
+                    chars.next();
+                    pattern.push_str("(?:.*/)?");
+                } else {
+                    pattern.push_str(".*");
+                }
+            },
+
+            '*' => pattern.push_str("[^/]*"),
+
+            '?' => pattern.push_str("[^/]"),
+
+            _ => {
+                let mut class = String::new();
+                let mut closed = false;
+
+                while let Some(c) = chars.next() {
+                    match c {
+                        ']' if !class.is_empty() => {
+                            closed = true;
+                            break;
+                        },
+                        '!' if class.is_empty() => class.push('^'),
+                        '\\' | '[' | '&' | '~' => {
+                            class.push('\\');
+                            class.push(c);
+                        },
+                        _ => class.push(c)
+                    }
+                }
+
+                if closed {
+                    pattern.push_str(&format!("[{}]", class));
+                } else {
+                    // an unterminated class is just a literal `[`
+                    pattern.push_str(&regex::escape(&format!("[{}", class)));
+                }
+            }
+        }
+    }
+
+    push_literal(&mut pattern, &mut literal);
+    pattern.push('$');
+
+    pattern
+}
+
+fn push_literal(pattern: &mut String, literal: &mut String) {
+    if !literal.is_empty() {
+        pattern.push_str(&format!("({})", regex::escape(literal)));
+        literal.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn paths(filter: &Filter, paths: &[&str]) -> Vec<String> {
+        let paths: Vec<String> = paths.iter().map(|&path| path.to_owned()).collect();
+        filter.matches(paths.iter()).into_iter().map(|m| m.path).collect()
+    }
+
+    #[test]
+    fn glob_translation() {
+        assert_eq!(r"^(src/)(?:.*/)?[^/]*(\.rs)$", glob_to_regex("src/**/*.rs"));
+        assert_eq!(r"^(?:.*/)?(a)[^/](b)[^c]$", glob_to_regex("a?b[!c]"));
+    }
+
+    #[test]
+    fn glob_matches() {
+        let filter = Filter::glob("src/**/*.rs", false).unwrap();
+        let matched = paths(&filter, &["src/main.rs", "src/a/b/c.rs", "tests/main.rs", "src/main.rs.orig"]);
+
+        assert_eq!(vec!["src/main.rs", "src/a/b/c.rs"], matched);
+    }
+
+    #[test]
+    fn glob_without_slash_matches_file_name() {
+        let filter = Filter::glob("*.md", false).unwrap();
+        let matched = paths(&filter, &["README.md", "docs/guide.md", "docs/md/guide"]);
+
+        assert_eq!(vec!["README.md", "docs/guide.md"], matched);
+    }
+
+    #[test]
+    fn glob_positions() {
+        let filter = Filter::glob("s*/*.rs", false).unwrap();
+        let matches = filter.matches([String::from("src/main.rs")].iter());
+
+        assert_eq!(vec![0, 3, 8, 9, 10], matches[0].positions);
+    }
+
+    #[test]
+    fn regex_matches() {
+        let filter = Filter::regex(r"ma.n\.", false).unwrap();
+        let matched = paths(&filter, &["src/main.rs", "src/Main.rs", "src/lib.rs"]);
+
+        assert_eq!(vec!["src/main.rs"], matched);
+    }
+
+    #[test]
+    fn regex_positions() {
+        let filter = Filter::regex("s", true).unwrap();
+        let matches = filter.matches([String::from("Src/s.rs")].iter());
+
+        assert_eq!(vec![0, 4, 7], matches[0].positions);
+    }
+
+    #[test]
+    fn invalid_regex() {
+        assert!(Filter::regex("src/(", false).is_none());
+    }
+}
@@ -2,20 +2,31 @@ use std::collections::HashMap;
 use std::convert::From;
 use std::fmt;
 use std::path::Path;
-use std::io::BufRead;
+use std::process::{Command, Stdio};
+use std::io::{self, BufRead};
 
 use ignore::Walk;
 
+use fields::Fields;
 use jump::{Jumps, Jump};
-use query::Query;
+use location::LocationLabel;
+use normalize::Normalization;
+use query_result::QueryResult;
+
+pub use location::WordBoundaries;
+use query::{Query, CaseMatching, Match};
 
 pub type Node = String;
 
+// the edge to the end of a path is keyed on `$`, the same as a literal `$`
+// in the path, so it is marked as the end to keep the two apart
 #[derive(Debug)]
 pub struct Edge {
     pub path: String,
     pub score: usize,
-    pub position: usize
+    pub position: usize,
+    pub contiguous: bool,
+    pub end: bool
 }
 
 impl From<Jump> for Edge {
@@ -23,7 +34,9 @@ impl From<Jump> for Edge {
         Edge {
             path: jump.destination.prefix,
             score: jump.score,
-            position: jump.destination.index
+            position: jump.destination.index,
+            contiguous: jump.contiguous,
+            end: jump.destination.label == LocationLabel::PathEnd
         }
     }
 }
@@ -32,47 +45,159 @@ pub type Edges = HashMap<char, Vec<Edge>>;
 
 pub type Graph = HashMap<String, Edges>;
 
-pub fn from_path<P: AsRef<Path>>(root: P) -> Index {
-    let mut index = Index::new();
+// every file under the root, along with whatever couldn't be read on the
+// way. A path that isn't valid UTF-8 is indexed with whatever can't be
+// decoded replaced
+pub fn from_path<P: AsRef<Path>>(root: P, options: Options) -> (Index, Vec<String>) {
+    let mut index = Index::with_options(options);
+    let mut errors = Vec::new();
 
     for result in Walk::new(&root) {
         match result {
             Ok(entry) => {
                 if entry.file_type().map(|ft| ft.is_file()).unwrap_or(false) {
                     let relative_path = entry.path().strip_prefix(&root).expect("make path relative");
-                    index.push(relative_path.to_str().expect("unable to convert path to str"))
+                    index.push(&relative_path.to_string_lossy())
                 }
             },
-            Err(err) => println!("ERROR: {}", err)
+            Err(err) => errors.push(err.to_string())
+        }
+    }
+
+    (index, errors)
+}
+
+// a line that isn't valid UTF-8 is still indexed, with whatever can't be
+// decoded replaced
+pub fn from_buf_reader<T: BufRead>(source: T, options: Options) -> io::Result<Index> {
+    let mut index = Index::with_options(options);
+
+    for line in source.split(b'\n') {
+        let mut line = line?;
+
+        if line.last() == Some(&b'\r') {
+            line.pop();
         }
+
+        index.push(&String::from_utf8_lossy(&line));
     }
 
-    return index;
+    Ok(index)
 }
 
-pub fn from_buf_reader<T: BufRead>(source: T) -> Index {
-    let mut index = Index::new();
+// the lines a command writes out, it can be run again to reload them. A
+// command that fails is an error, with the first line it wrote to stderr
+pub fn from_command(command: &str, options: Options) -> io::Result<Index> {
+    let output = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .stdin(Stdio::null())
+        .output()?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+
+        let message = match stderr.lines().map(|line| line.trim()).find(|line| !line.is_empty()) {
+            Some(line) => line.to_owned(),
+            None => output.status.to_string()
+        };
 
-    for line in source.lines() {
-        index.push(&line.unwrap());
+        return Err(io::Error::new(io::ErrorKind::Other, message));
     }
 
-    return index;
+    from_buf_reader(&output.stdout[..], options)
 }
 
-pub struct Index {
-    graph: Graph
+// what is being indexed, paths get a lower score the shallower they are,
+// arbitrary lines of text are all treated the same
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Path,
+    Text
+}
+
+impl Default for Kind {
+    fn default() -> Kind {
+        Kind::Path
+    }
+}
+
+// how paths are broken up when they are added to an index, these can't be
+// changed without building the index again
+#[derive(Debug, Clone, Default)]
+pub struct Options {
+    pub kind: Kind,
+    pub normalization: Normalization,
+    pub word_boundaries: WordBoundaries,
+    pub fields: Fields
+}
+
+// a line added to the index, when only some of its fields are matched
+// against the offsets map each byte of that key back to the line. The
+// payload is handed back with every result for the line
+struct Entry<T> {
+    line: String,
+    offsets: Option<Vec<usize>>,
+    payload: T
+}
+
+impl<T> Entry<T> {
+    fn line_position(&self, position: usize) -> usize {
+        match self.offsets {
+            Some(ref offsets) => offsets.get(position).cloned().unwrap_or(self.line.len()),
+            None => position
+        }
+    }
+}
+
+pub struct Index<T = ()> {
+    graph: Graph,
+    entries: Vec<Entry<T>>,
+    keys: HashMap<String, Vec<usize>>,
+    options: Options
 }
 
 impl Index {
     pub fn new() -> Index {
+        Index::with_options(Options::default())
+    }
+
+    pub fn with_options(options: Options) -> Index {
+        Index::with_payloads(options)
+    }
+
+    pub fn push(&mut self, s: &str) {
+        self.push_with_payload(s, ());
+    }
+}
+
+impl<T> Index<T> {
+    // an index where each line carries some data of its own, like an id
+    // for the record it came from
+    pub fn with_payloads(options: Options) -> Index<T> {
         Index {
-            graph: HashMap::new()
+            graph: HashMap::new(),
+            entries: Vec::new(),
+            keys: HashMap::new(),
+            options: options
         }
     }
 
-    pub fn push(&mut self, s: &str) {
-        let jumps = Jumps::new(s);
+    pub fn push_with_payload(&mut self, s: &str, payload: T) {
+        let (key, offsets) = match self.options.fields.key(s) {
+            Some((key, offsets)) => (key, Some(offsets)),
+            None => (s.to_owned(), None)
+        };
+
+        self.keys.entry(key.clone()).or_insert(Vec::new()).push(self.entries.len());
+        self.entries.push(Entry { line: s.to_owned(), offsets: offsets, payload: payload });
+
+        // lines with the same key are only added to the graph once
+        if self.keys[&key].len() > 1 {
+            return;
+        }
+
+        let jumps = Jumps::new(&key, &self.options);
 
         for jump in jumps {
             self.graph
@@ -84,12 +209,63 @@ impl Index {
         }
     }
 
-    pub fn query(&self) -> Query {
-        Query::new(&self.graph)
+    pub fn graph(&self) -> &Graph {
+        &self.graph
+    }
+
+    // the distinct strings that are matched against
+    pub fn keys(&self) -> ::std::collections::hash_map::Keys<String, Vec<usize>> {
+        self.keys.keys()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    // how many lines a matched key turns into
+    pub fn lines_with(&self, key: &str) -> usize {
+        self.keys.get(key).map(|entries| entries.len()).unwrap_or(0)
+    }
+
+    pub fn normalization(&self) -> Normalization {
+        self.options.normalization
     }
 }
 
-impl fmt::Debug for Index {
+impl<T: Clone> Index<T> {
+    pub fn query(&self) -> Query<T> {
+        self.query_with_case_matching(CaseMatching::default())
+    }
+
+    pub fn query_with_case_matching(&self, case_matching: CaseMatching) -> Query<T> {
+        Query::new(self, case_matching)
+    }
+
+    // every line with the matched key becomes a result, with the positions
+    // moved from the key to the line
+    pub fn results(&self, m: &Match) -> Vec<QueryResult<T>> {
+        let entries = match self.keys.get(&m.path) {
+            Some(entries) => entries,
+            None => return Vec::new()
+        };
+
+        entries.iter()
+            .map(|&i| {
+                let entry = &self.entries[i];
+
+                QueryResult {
+                    path: entry.line.clone(),
+                    score: m.score,
+                    positions: m.positions.iter().map(|&position| entry.line_position(position)).collect(),
+                    display: self.options.fields.display(&entry.line),
+                    payload: entry.payload.clone()
+                }
+            })
+            .collect()
+    }
+}
+
+impl<T> fmt::Debug for Index<T> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         try!(write!(f, "\n"));
         for (location, edge_map) in &self.graph {
@@ -109,7 +285,14 @@ impl fmt::Debug for Index {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use query_result::*;
+    use std::{env, fs, process};
+    use std::io::Read;
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+
+    use fields::parse_ranges;
+    use normalize::Form;
+    use query::Mode;
 
     #[test]
     fn query_match() {
@@ -128,4 +311,626 @@ mod tests {
         assert_eq!("fab/cab/dab", results[0].path);
         assert_eq!("foo/bar/baz", results[1].path);
     }
-}
\ No newline at end of file
+
+    fn paths(query: &Query) -> Vec<String> {
+        let mut paths: Vec<String> = query.results().map(|result| result.path).collect();
+        paths.sort();
+        paths
+    }
+
+    #[test]
+    fn case_insensitive_match() {
+        let mut index = Index::new();
+        index.push("Foo");
+        index.push("foo");
+
+        let mut query = index.query_with_case_matching(CaseMatching::Insensitive);
+
+        query.advance('F');
+        assert_eq!(vec!["Foo", "foo"], paths(&query));
+
+        query.back();
+        query.advance('f');
+        assert_eq!(vec!["Foo", "foo"], paths(&query));
+    }
+
+    #[test]
+    fn case_sensitive_match() {
+        let mut index = Index::new();
+        index.push("Foo");
+        index.push("foo");
+
+        let mut query = index.query_with_case_matching(CaseMatching::Sensitive);
+
+        query.advance('F');
+        assert_eq!(vec!["Foo"], paths(&query));
+
+        query.back();
+        query.advance('f');
+        assert_eq!(vec!["foo"], paths(&query));
+    }
+
+    #[test]
+    fn smart_case_match() {
+        let mut index = Index::new();
+        index.push("fooBar");
+        index.push("foobar");
+
+        let mut query = index.query_with_case_matching(CaseMatching::Smart);
+
+        query.advance('f');
+        query.advance('b');
+        assert_eq!(vec!["fooBar", "foobar"], paths(&query));
+
+        query.back();
+        query.advance('B');
+        assert_eq!(vec!["fooBar"], paths(&query));
+
+        query.back();
+        assert_eq!(vec!["fooBar", "foobar"], paths(&query));
+    }
+
+    #[test]
+    fn normalized_match() {
+        let mut index = Index::new();
+        index.push("cafe\u{301}/menu");
+
+        let mut query = index.query();
+
+        for c in "café".chars() {
+            query.advance(c);
+        }
+
+        let results: Vec<QueryResult> = query.results().collect();
+
+        assert_eq!(1, results.len());
+        assert_eq!("cafe\u{301}/menu", results[0].path);
+        assert!(results[0].positions.contains(&3));
+        assert!(!results[0].positions.contains(&4));
+    }
+
+    #[test]
+    fn lone_dollar_match() {
+        let mut index = Index::new();
+        index.push("price$/list");
+        index.push("src/main.rs");
+
+        let mut query = index.query();
+        query.advance('$');
+
+        let results: Vec<QueryResult> = query.results().collect();
+
+        assert_eq!(1, results.len());
+        assert_eq!("price$/list", results[0].path);
+        assert_eq!(vec![5], results[0].positions.iter().cloned().collect::<Vec<usize>>());
+    }
+
+    #[test]
+    fn decomposed_hangul_match() {
+        let mut index = Index::new();
+        index.push("\u{1112}\u{1161}\u{11AB}/file");
+        index.push("한/file");
+
+        let mut query = index.query();
+        query.advance('한');
+
+        assert_eq!(vec!["\u{1112}\u{1161}\u{11AB}/file", "한/file"], paths(&query));
+    }
+
+    #[test]
+    fn decomposed_query_match() {
+        let mut index = Index::new();
+        index.push("cafe\u{301}/menu");
+        index.push("café/menu");
+        index.push("école/menu");
+        index.push("cafe/menu");
+
+        let query_for = |query_string: &str| {
+            let mut query = index.query();
+
+            for c in query_string.chars() {
+                query.advance(c);
+            }
+
+            query
+        };
+
+        let mut query = query_for("cafe\u{301}");
+        assert_eq!(vec!["cafe\u{301}/menu", "café/menu"], paths(&query));
+
+        // without the accent the e of menu is as good as any
+        query.back();
+        assert_eq!(vec!["cafe/menu", "cafe\u{301}/menu", "café/menu"], paths(&query));
+
+        assert_eq!(vec!["école/menu"], paths(&query_for("e\u{301}cole")));
+        assert!(paths(&query_for("\u{1112}\u{1161}\u{11AB}")).is_empty());
+    }
+
+    #[test]
+    fn full_case_folding_match() {
+        let mut index = Index::new();
+        index.push("Straße");
+
+        let mut query = index.query_with_case_matching(CaseMatching::Insensitive);
+
+        for c in "STRASS".chars() {
+            query.advance(c);
+        }
+
+        assert_eq!(vec!["Straße"], paths(&query));
+
+        for _ in 0..2 {
+            query.back();
+        }
+
+        query.advance('ß');
+
+        assert_eq!(vec!["Straße"], paths(&query));
+    }
+
+    #[test]
+    fn diacritic_insensitive_match() {
+        let normalization = Normalization { form: Form::Nfkc, ignore_diacritics: true };
+        let mut index = Index::with_options(Options { normalization: normalization, ..Options::default() });
+        index.push("résumé.pdf");
+
+        let mut query = index.query();
+
+        query.advance('r');
+        query.advance('e');
+        query.advance('s');
+
+        let results: Vec<QueryResult> = query.results().collect();
+
+        assert_eq!(1, results.len());
+        assert!(results[0].positions.contains(&1));
+
+        query.back();
+        query.back();
+        query.advance('é');
+
+        assert_eq!(vec!["résumé.pdf"], paths(&query));
+    }
+
+    #[test]
+    fn multiple_term_match() {
+        let mut index = Index::new();
+        index.push("src/query.rs");
+        index.push("src/index.rs");
+        index.push("tests/query.rs");
+
+        let mut query = index.query();
+
+        for c in "query src".chars() {
+            query.advance(c);
+        }
+
+        let results: Vec<QueryResult> = query.results().collect();
+
+        assert_eq!(1, results.len());
+        assert_eq!("src/query.rs", results[0].path);
+
+        let mut positions: Vec<usize> = results[0].positions.iter().cloned().collect();
+
+        positions.sort();
+        assert_eq!(vec![0, 1, 2, 4, 5, 6, 7, 8], positions);
+    }
+
+    #[test]
+    fn multiple_term_back() {
+        let mut index = Index::new();
+        index.push("src/query.rs");
+        index.push("tests/query.rs");
+
+        let mut query = index.query();
+
+        for c in "qu  src".chars() {
+            query.advance(c);
+        }
+
+        assert_eq!("qu  src", query.query_string());
+        assert_eq!(vec!["src/query.rs"], paths(&query));
+
+        for _ in 0..4 {
+            query.back();
+        }
+
+        assert_eq!("qu ", query.query_string());
+        assert_eq!(vec!["src/query.rs", "tests/query.rs"], paths(&query));
+
+        query.back();
+        query.back();
+
+        assert_eq!("q", query.query_string());
+    }
+
+    fn query_paths(index: &Index, query_string: &str) -> Vec<String> {
+        let mut query = index.query();
+
+        for c in query_string.chars() {
+            query.advance(c);
+        }
+
+        paths(&query)
+    }
+
+    fn query_paths_ranked(index: &Index, query_string: &str) -> Vec<String> {
+        let mut query = index.query();
+
+        for c in query_string.chars() {
+            query.advance(c);
+        }
+
+        query.results().map(|result| result.path).collect()
+    }
+
+    fn operator_index() -> Index {
+        let mut index = Index::new();
+        index.push("src/query.rs");
+        index.push("src/query_result.rs");
+        index.push("tests/query.rs");
+        index.push("README.md");
+        index
+    }
+
+    #[test]
+    fn negated_match() {
+        let index = operator_index();
+
+        assert_eq!(vec!["src/query.rs", "src/query_result.rs"], query_paths(&index, "qr !tests"));
+        assert_eq!(vec!["README.md", "src/query.rs", "src/query_result.rs"], query_paths(&index, "!tests"));
+    }
+
+    #[test]
+    fn exact_match() {
+        let index = operator_index();
+
+        assert_eq!(vec!["src/query.rs", "src/query_result.rs", "tests/query.rs"], query_paths(&index, "qrs"));
+        assert_eq!(vec!["src/query_result.rs"], query_paths(&index, "'y_r"));
+    }
+
+    #[test]
+    fn anchored_start_match() {
+        let index = operator_index();
+
+        assert_eq!(vec!["src/query.rs", "src/query_result.rs"], query_paths(&index, "^src"));
+        assert_eq!(Vec::<String>::new(), query_paths(&index, "^query"));
+    }
+
+    #[test]
+    fn anchored_end_match() {
+        let index = operator_index();
+
+        assert_eq!(vec!["src/query.rs", "tests/query.rs"], query_paths(&index, "query.rs$"));
+        assert_eq!(vec!["README.md"], query_paths(&index, ".md$"));
+    }
+
+    #[test]
+    fn or_match() {
+        let index = operator_index();
+
+        assert_eq!(vec!["README.md", "tests/query.rs"], query_paths(&index, "^tests | .md$"));
+        assert_eq!(vec!["tests/query.rs"], query_paths(&index, "rs ^tests | .md$"));
+    }
+
+    #[test]
+    fn operators_are_incremental() {
+        let index = operator_index();
+        let mut query = index.query();
+
+        for c in "^src".chars() {
+            query.advance(c);
+        }
+
+        query.advance('$');
+        assert_eq!(Vec::<String>::new(), paths(&query));
+
+        query.back();
+        assert_eq!(vec!["src/query.rs", "src/query_result.rs"], paths(&query));
+
+        for _ in 0..4 {
+            query.back();
+        }
+
+        assert_eq!("", query.query_string());
+        assert_eq!(4, paths(&query).len());
+    }
+
+    #[test]
+    fn glob_and_regex_modes() {
+        let index = operator_index();
+        let mut query = index.query();
+
+        for c in "*.rs".chars() {
+            query.advance(c);
+        }
+
+        query.set_mode(Mode::Glob);
+        assert_eq!(vec!["src/query.rs", "src/query_result.rs", "tests/query.rs"], paths(&query));
+
+        query.set_mode(Mode::Regex);
+        assert_eq!(Vec::<String>::new(), paths(&query));
+
+        for _ in 0..4 {
+            query.back();
+        }
+
+        for c in "^(R|t)".chars() {
+            query.advance(c);
+        }
+
+        assert_eq!(vec!["README.md", "tests/query.rs"], paths(&query));
+
+        query.set_mode(Mode::Fuzzy);
+        assert_eq!("^(R|t)", query.query_string());
+        assert_eq!(Vec::<String>::new(), paths(&query));
+    }
+
+    #[test]
+    fn typo_tolerant_match() {
+        let mut index = Index::new();
+        index.push("src/query.rs");
+        index.push("src/index.rs");
+
+        let mut query = index.query();
+
+        for c in "qeury".chars() {
+            query.advance(c);
+        }
+
+        assert_eq!(Vec::<String>::new(), paths(&query));
+
+        query.set_max_typos(1);
+        assert_eq!(vec!["src/query.rs"], paths(&query));
+
+        query.back();
+        query.back();
+        query.back();
+        query.back();
+
+        for c in "uxery".chars() {
+            query.advance(c);
+        }
+
+        assert_eq!(vec!["src/query.rs"], paths(&query));
+
+        query.advance('z');
+        assert_eq!(Vec::<String>::new(), paths(&query));
+    }
+
+    #[test]
+    fn leading_typo_match() {
+        let mut index = Index::new();
+        index.push("src/query.rs");
+        index.push("src/index.rs");
+
+        let mut query = index.query();
+        query.set_max_typos(1);
+
+        for c in "wuery".chars() {
+            query.advance(c);
+        }
+
+        assert_eq!(vec!["src/query.rs"], paths(&query));
+
+        // a single character that is skipped leaves nothing to match
+        for _ in 0..4 {
+            query.back();
+        }
+
+        assert_eq!(Vec::<String>::new(), paths(&query));
+    }
+
+    #[test]
+    fn typos_rank_below_exact_matches() {
+        let mut index = Index::new();
+        index.push("abc/xyz");
+        index.push("acb/xyz");
+
+        let mut query = index.query();
+        query.set_max_typos(1);
+
+        for c in "acb".chars() {
+            query.advance(c);
+        }
+
+        let results: Vec<QueryResult> = query.results().collect();
+
+        assert_eq!(2, results.len());
+        assert_eq!("acb/xyz", results[0].path);
+        assert_eq!("abc/xyz", results[1].path);
+    }
+
+    #[test]
+    fn word_boundaries_change_ranking() {
+        let paths = ["app.config.test.ts", "app/contest.ts", "src/actest.ts"];
+
+        let mut index = Index::new();
+        let word_boundaries = WordBoundaries { characters: vec!['.', '/'], digits: false };
+        let mut dotted_index = Index::with_options(Options { word_boundaries: word_boundaries, ..Options::default() });
+
+        for path in paths.iter() {
+            index.push(path);
+            dotted_index.push(path);
+        }
+
+        assert_eq!("src/actest.ts", query_paths_ranked(&index, "ctt")[0]);
+        assert_eq!("app.config.test.ts", query_paths_ranked(&dotted_index, "ctt")[0]);
+    }
+
+    #[test]
+    fn text_match() {
+        let lines = ["ant/b/c/d/e", "main"];
+
+        let mut index = Index::new();
+        let mut text_index = Index::with_options(Options { kind: Kind::Text, ..Options::default() });
+
+        for line in lines.iter() {
+            index.push(line);
+            text_index.push(line);
+        }
+
+        assert_eq!("main", query_paths_ranked(&index, "an")[0]);
+        assert_eq!("ant/b/c/d/e", query_paths_ranked(&text_index, "an")[0]);
+    }
+
+    #[test]
+    fn field_match() {
+        let mut fields = Fields::default();
+        fields.delimiter = Some(String::from(":"));
+        fields.nth = parse_ranges("1").unwrap();
+        fields.with_nth = parse_ranges("1,3").unwrap();
+
+        let mut index = Index::with_options(Options { fields: fields, ..Options::default() });
+        index.push("src/main.rs:42: fn main");
+        index.push("src/lib.rs:7: main entry");
+        index.push("src/main.rs:50: fn helper");
+
+        let mut query = index.query();
+
+        for c in "main".chars() {
+            query.advance(c);
+        }
+
+        let results: Vec<QueryResult> = query.results().collect();
+        let mut lines: Vec<&str> = results.iter().map(|result| result.path.as_ref()).collect();
+        lines.sort();
+
+        assert_eq!(vec!["src/main.rs:42: fn main", "src/main.rs:50: fn helper"], lines);
+        assert_eq!(vec![(0, 11), (15, 23)], results[0].display);
+        assert!(results.iter().all(|result| result.positions.contains(&4)));
+    }
+
+    #[test]
+    fn payload_match() {
+        let mut index: Index<usize> = Index::with_payloads(Options::default());
+        index.push_with_payload("src/main.rs", 1);
+        index.push_with_payload("src/lib.rs", 2);
+        index.push_with_payload("src/main.rs", 3);
+
+        let mut query = index.query();
+
+        for c in "main".chars() {
+            query.advance(c);
+        }
+
+        let mut payloads: Vec<usize> = query.results().map(|result| result.payload).collect();
+        payloads.sort();
+
+        assert_eq!(vec![1, 3], payloads);
+    }
+
+    #[test]
+    fn edit_query_string() {
+        let index = operator_index();
+        let mut query = index.query();
+
+        query.set_query_string("query rs");
+        query.set_query_string("qresult");
+
+        assert_eq!("qresult", query.query_string());
+        assert_eq!(query_paths(&index, "qresult"), paths(&query));
+        assert_eq!(vec!["src/query_result.rs"], paths(&query));
+    }
+
+    #[test]
+    fn threads_agree() {
+        let mut index = Index::new();
+
+        for i in 0..3000 {
+            index.push(&format!("src/mod{}/sub{}/file{}.rs", i % 7, i % 13, i));
+        }
+
+        let results = |threads: usize| {
+            let mut query = index.query();
+            query.set_threads(threads);
+            query.set_query_string("sfi1");
+
+            let mut results: Vec<(String, usize, Vec<usize>)> = query.results()
+                .map(|result| {
+                    let mut positions: Vec<usize> = result.positions.into_iter().collect();
+                    positions.sort();
+                    (result.path, result.score, positions)
+                })
+                .collect();
+
+            results.sort();
+            results
+        };
+
+        let single = results(1);
+
+        assert!(!single.is_empty());
+        assert_eq!(single, results(4));
+    }
+
+    #[test]
+    fn directory_walk() {
+        let root = env::temp_dir().join(format!("ff-walk-{}", process::id()));
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::write(root.join("src").join("main.rs"), "").unwrap();
+        fs::write(root.join("Cargo.toml"), "").unwrap();
+        fs::write(root.join(OsStr::from_bytes(b"caf\xe9.rs")), "").unwrap();
+
+        let (index, errors) = from_path(&root, Options::default());
+        fs::remove_dir_all(&root).ok();
+
+        assert_eq!(vec!["Cargo.toml", "caf\u{FFFD}.rs", "src/main.rs"], paths(&index.query()));
+        assert!(errors.is_empty());
+    }
+
+    #[test]
+    fn lines_that_are_not_utf8() {
+        let input: &[u8] = b"src/main.rs\r\nsrc/caf\xe9.rs\nREADME.md";
+        let index = from_buf_reader(input, Options::default()).unwrap();
+
+        assert_eq!(vec!["README.md", "src/caf\u{FFFD}.rs", "src/main.rs"], paths(&index.query()));
+    }
+
+    #[test]
+    fn command_output() {
+        let index = from_command("printf 'src/main.rs\\nCargo.toml\\n'; echo ignored >&2", Options::default()).unwrap();
+
+        assert_eq!(vec!["Cargo.toml", "src/main.rs"], paths(&index.query()));
+    }
+
+    #[test]
+    fn failing_commands() {
+        let error = from_command("echo partial; echo 'no such thing' >&2; exit 3", Options::default()).err().unwrap();
+        assert_eq!("no such thing", error.to_string());
+
+        let error = from_command("false", Options::default()).err().unwrap();
+        assert_eq!("exit status: 1", error.to_string());
+    }
+
+    #[test]
+    fn read_errors() {
+        struct Failing;
+
+        impl Read for Failing {
+            fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+                Err(io::Error::new(io::ErrorKind::Other, "disconnected"))
+            }
+        }
+
+        let error = from_buf_reader(io::BufReader::new(Failing), Options::default()).err().unwrap();
+        assert_eq!("disconnected", error.to_string());
+    }
+
+    #[test]
+    fn reloading_runs_the_command_again() {
+        let file = env::temp_dir().join(format!("ff-reload-{}", process::id()));
+        let command = format!("cat '{}'", file.display());
+
+        fs::write(&file, "a.rs\nb.rs\n").unwrap();
+        let before = from_command(&command, Options::default()).unwrap();
+
+        fs::write(&file, "c.rs\n").unwrap();
+        let after = from_command(&command, Options::default()).unwrap();
+
+        fs::remove_file(&file).ok();
+
+        assert_eq!(vec!["a.rs", "b.rs"], paths(&before.query()));
+        assert_eq!(vec!["c.rs"], paths(&after.query()));
+    }
+}
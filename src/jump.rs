@@ -1,28 +1,28 @@
 use location::{Location, Locations, LocationLabel};
+use index::Options;
 
 #[derive(Debug)]
 pub struct Jump {
     pub source: Location,
     pub destination: Location,
-    pub score: usize
+    pub score: usize,
+    pub contiguous: bool
 }
 
 pub struct Jumps {
     locations: Vec<Location>,
     source_index: usize,
-    destination_index: usize,
-    upper_jumps: Vec<Jump>
+    destination_index: usize
 }
 
 impl Jumps {
-    pub fn new(path: &str) -> Self {
-        let locations = Locations::new(path).collect();
+    pub fn new(path: &str, options: &Options) -> Self {
+        let locations = Locations::new(path, options).collect();
 
         Jumps {
             locations: locations,
             source_index: 0,
-            destination_index: 1,
-            upper_jumps: Vec::new()
+            destination_index: 1
         }
     }
 }
@@ -31,10 +31,6 @@ impl Iterator for Jumps {
     type Item = Jump;
 
     fn next(&mut self) -> Option<Jump> {
-        if self.upper_jumps.len() > 0 {
-            return self.upper_jumps.pop();
-        }
-
         if self.destination_index >= self.locations.len() {                                                                                                             
             self.source_index = self.source_index + 1;                                                                                                        
             self.destination_index  = self.source_index + 1;                                                                                                       
@@ -47,27 +43,14 @@ impl Iterator for Jumps {
         let source = &self.locations[self.source_index];
         let destination = &self.locations[self.destination_index];
 
-        let score = calculate_score(&source, &destination);
-
-        if destination.character.is_uppercase() {
-            for lower_destination_character in destination.character.to_lowercase() {
-                let mut lower_destination = destination.clone();
-                lower_destination.character = lower_destination_character;
-
-                self.upper_jumps.push(
-                    Jump {
-                        source: source.clone(),
-                        destination: lower_destination,
-                        score: score - 1
-                    }
-                )
-            }
-        }
+        let contiguous = self.destination_index == self.source_index + 1;
+        let score = calculate_score(&source, &destination, contiguous);
 
         let jump = Jump {
             source: source.clone(),
             destination: destination.clone(),
-            score: score
+            score: score,
+            contiguous: contiguous
         };
                                                                                                                                                               
         self.destination_index = self.destination_index + 1;                                                                                                            
@@ -76,7 +59,7 @@ impl Iterator for Jumps {
     }
 }
 
-fn calculate_score(source: &Location, destination: &Location) -> usize {
+fn calculate_score(source: &Location, destination: &Location, contiguous: bool) -> usize {
     let score = destination.height;
 
     match (source.label, destination.label) {
@@ -101,7 +84,7 @@ fn calculate_score(source: &Location, destination: &Location) -> usize {
 
         _ => {
             // if the locations are contiguous then its _very_ cheap
-            if destination.index == source.index + 1 {
+            if contiguous {
                 score + 1
             
             // otherwise its expensive
@@ -118,7 +101,7 @@ mod tests {
 
     #[test]
     fn jump_count() {
-        let jumps = Jumps::new("ab");
+        let jumps = Jumps::new("ab", &Options::default());
         // ^ => a | ^ => b | ^ => $
         // a => b | a => $
         // b => $
@@ -131,7 +114,7 @@ mod tests {
         // a => b | a => $
         // b => $
 
-        let actual: Vec<char> = Jumps::new("ab")
+        let actual: Vec<char> = Jumps::new("ab", &Options::default())
             .map(|jump| jump.source.character)
             .collect();
 
@@ -150,7 +133,7 @@ mod tests {
         // a => b | a => $
         // b => $
 
-        let actual: Vec<char> = Jumps::new("ab")
+        let actual: Vec<char> = Jumps::new("ab", &Options::default())
             .map(|jump| jump.destination.character)
             .collect();
 
@@ -169,7 +152,7 @@ mod tests {
         // a => b | a => $
         // b => $
 
-        let actual: Vec<usize> = Jumps::new("ab")
+        let actual: Vec<usize> = Jumps::new("ab", &Options::default())
             .map(|jump| jump.score)
             .collect();
 
@@ -184,16 +167,37 @@ mod tests {
 
     #[test]
     fn mixed_case_jumps() {
-        let actual: Vec<char> = Jumps::new("aB")
+        // case folding happens at query time, so only the original
+        // characters should end up as destinations
+        let actual: Vec<char> = Jumps::new("aB", &Options::default())
             .map(|jump| jump.destination.character)
             .collect();
 
         let expected = vec![
-            'a', 'B', 'b', '$',
-            'B', 'b', '$',
+            'a', 'B', '$',
+            'B', '$',
             '$'
         ];
 
         assert_eq!(expected, actual);
     }
+
+    #[test]
+    fn jump_contiguous() {
+        // ^ => a | ^ => b | ^ => $
+        // a => b | a => $
+        // b => $
+
+        let actual: Vec<bool> = Jumps::new("ab", &Options::default())
+            .map(|jump| jump.contiguous)
+            .collect();
+
+        let expected = vec![
+            true, false, false,
+            true, false,
+            true
+        ];
+
+        assert_eq!(expected, actual);
+    }
 }
@@ -0,0 +1,134 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Sender, Receiver};
+use std::thread;
+
+// generations are counted across every worker, so that an answer from a
+// worker that has since been replaced can't be taken for one from its
+// replacement
+static GENERATION: AtomicUsize = AtomicUsize::new(0);
+
+// requests handled one at a time on a thread of their own, where every
+// request supersedes the ones before it. Requests that piled up are skipped
+// and the one being handled can check whether it has been overtaken, so
+// that it stops as soon as it notices
+pub struct Latest<R> {
+    requests: Sender<(usize, R)>,
+    latest: Arc<AtomicUsize>
+}
+
+pub struct Job<R> {
+    pub generation: usize,
+    pub request: R,
+    latest: Arc<AtomicUsize>
+}
+
+impl<R> Job<R> {
+    pub fn cancelled(&self) -> bool {
+        self.latest.load(Ordering::SeqCst) != self.generation
+    }
+}
+
+// the newest request each time there is one, until the worker is dropped
+pub struct Jobs<R> {
+    received: Receiver<(usize, R)>,
+    latest: Arc<AtomicUsize>
+}
+
+impl<R> Iterator for Jobs<R> {
+    type Item = Job<R>;
+
+    fn next(&mut self) -> Option<Job<R>> {
+        let mut newest = self.received.recv().ok()?;
+
+        while let Ok(newer) = self.received.try_recv() {
+            newest = newer;
+        }
+
+        Some(Job {
+            generation: newest.0,
+            request: newest.1,
+            latest: self.latest.clone()
+        })
+    }
+}
+
+impl<R: Send + 'static> Latest<R> {
+    pub fn start<F>(work: F) -> Latest<R>
+        where F: FnOnce(Jobs<R>) + Send + 'static
+    {
+        let (requests, received): (Sender<(usize, R)>, Receiver<(usize, R)>) = mpsc::channel();
+        let latest = Arc::new(AtomicUsize::new(0));
+        let jobs = Jobs { received: received, latest: latest.clone() };
+
+        thread::spawn(move || work(jobs));
+
+        Latest {
+            requests: requests,
+            latest: latest
+        }
+    }
+
+    // returns the generation of the job for this request
+    pub fn push(&self, request: R) -> usize {
+        let generation = GENERATION.fetch_add(1, Ordering::SeqCst) + 1;
+
+        self.latest.store(generation, Ordering::SeqCst);
+        self.requests.send((generation, request)).ok();
+
+        generation
+    }
+}
+
+// anything still being worked on is no longer wanted
+impl<R> Drop for Latest<R> {
+    fn drop(&mut self) {
+        self.latest.store(0, Ordering::SeqCst);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::time::Duration;
+
+    #[test]
+    fn overtaken_jobs_are_cancelled() {
+        let (sender, receiver) = mpsc::channel();
+        let (started, start) = mpsc::channel();
+
+        let latest = Latest::start(move |jobs| {
+            for job in jobs {
+                started.send(()).ok();
+
+                while !job.cancelled() && job.request == "slow" {
+                    thread::sleep(Duration::from_millis(1));
+                }
+
+                sender.send((job.request, job.cancelled())).ok();
+            }
+        });
+
+        latest.push("slow");
+        start.recv_timeout(Duration::from_secs(5)).unwrap();
+
+        let fast = latest.push("fast");
+
+        assert_eq!(("slow", true), receiver.recv_timeout(Duration::from_secs(5)).unwrap());
+        assert_eq!(("fast", false), receiver.recv_timeout(Duration::from_secs(5)).unwrap());
+        assert!(fast > 0);
+    }
+
+    #[test]
+    fn generations_are_never_shared() {
+        let first = Latest::start(|jobs: Jobs<()>| for _ in jobs {});
+        let second = Latest::start(|jobs: Jobs<()>| for _ in jobs {});
+
+        let a = first.push(());
+        let b = second.push(());
+        let c = first.push(());
+
+        assert!(a < b && b < c);
+    }
+}
@@ -1,14 +1,33 @@
 #![feature(inclusive_range_syntax)]
 extern crate termion;
 extern crate ignore;
+extern crate unicode_normalization;
+extern crate unicode_segmentation;
+extern crate unicode_width;
+extern crate caseless;
+extern crate regex;
+extern crate crossbeam;
+extern crate libc;
 
 mod location;
 mod jump;
 pub mod index;
 pub mod query;
+mod term;
 pub mod ui;
 mod min_set;
+pub mod fields;
+mod filter;
+mod syntax;
+pub mod normalize;
 pub mod query_result;
+pub mod search;
+mod latest;
+pub mod preview;
+pub mod action;
+pub mod theme;
+mod width;
+mod shell;
 
 #[cfg(test)]
 mod tests {
@@ -1,6 +1,9 @@
-use std::str::CharIndices;
+use std::vec::IntoIter;
 use std::collections::HashMap;
 
+use index::{Options, Kind};
+use normalize::Unit;
+
 #[derive(Debug, Eq, PartialEq, Clone, Copy)]
 pub enum LocationLabel {
     Simple,
@@ -9,6 +12,39 @@ pub enum LocationLabel {
     PathEnd
 }
 
+// the characters which begin a new word when they are followed by
+// something, along with whether switching between digits and letters
+// should also count. Uppercase characters always begin a word
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WordBoundaries {
+    pub characters: Vec<char>,
+    pub digits: bool
+}
+
+impl Default for WordBoundaries {
+    fn default() -> WordBoundaries {
+        WordBoundaries {
+            characters: vec!['_', '-', '/'],
+            digits: false
+        }
+    }
+}
+
+impl WordBoundaries {
+    fn label(&self, previous: char, character: char) -> LocationLabel {
+        if character.is_uppercase() || self.characters.contains(&previous) {
+            return LocationLabel::WordBegin;
+        }
+
+        if self.digits && previous.is_alphanumeric() && character.is_alphanumeric() &&
+            previous.is_numeric() != character.is_numeric() {
+            return LocationLabel::WordBegin;
+        }
+
+        LocationLabel::Simple
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Location {
     pub index: usize,
@@ -18,24 +54,36 @@ pub struct Location {
     pub label: LocationLabel
 }
 
-pub struct Locations<'a> {
-    chars: CharIndices<'a>,
+pub struct Locations {
+    units: IntoIter<Unit>,
+    word_boundaries: WordBoundaries,
     previous: Option<char>,
     height_map: HashMap<usize, usize>,
-    source: &'a str,
     path_begin: Option<Location>,
     path_end: Option<Location>
 }
 
-impl<'a> Locations<'a> {
-    pub fn new(source: &'a str) -> Self {
-        let chars = (&source).char_indices();
+impl Locations {
+    pub fn new(source: &str, options: &Options) -> Self {
         let mut height_map: HashMap<usize, usize> = HashMap::new();
+        let mut word_boundaries = options.word_boundaries.clone();
+
+        // lines of text aren't nested like paths, so everything is at the same
+        // height, and they are mostly made up of space separated words
+        let is_path = options.kind == Kind::Path;
 
-        let mut height = source.match_indices("/").collect::<Vec<_>>().len();
+        if !is_path {
+            for &c in [' ', '\t'].iter() {
+                if !word_boundaries.characters.contains(&c) {
+                    word_boundaries.characters.push(c);
+                }
+            }
+        }
 
-        for (index, c) in chars.clone() {
-            if c == '/' {
+        let mut height = if is_path { source.match_indices("/").collect::<Vec<_>>().len() } else { 0 };
+
+        for (index, c) in source.char_indices() {
+            if is_path && c == '/' {
                 height = height - 1;
             }
 
@@ -59,17 +107,17 @@ impl<'a> Locations<'a> {
         };
 
         Locations {
-            chars: chars,
+            units: options.normalization.units(source).into_iter(),
+            word_boundaries: word_boundaries,
             previous: None,
             height_map: height_map,
-            source: source,
             path_begin: Some(path_begin),
             path_end: Some(path_end)
         }
     }
 }
 
-impl<'a> Iterator for Locations<'a> {
+impl Iterator for Locations {
     type Item = Location;
 
     fn next(&mut self) -> Option<Location> {
@@ -77,24 +125,18 @@ impl<'a> Iterator for Locations<'a> {
             return self.path_begin.take();
         }
 
-        self.chars.next().and_then(|(index, character)| {
+        self.units.next().and_then(|Unit { index, character, prefix }| {
             let location = self.previous.as_ref()
-                .map(|previous_character| {
+                .map(|&previous_character| {
 
-                    let label = match (previous_character, character) {
-                        (_, a) if a.is_uppercase() => LocationLabel::WordBegin,
-                        (&'_', _) => LocationLabel::WordBegin,
-                        (&'-', _) => LocationLabel::WordBegin,
-                        (&'/', _) => LocationLabel::WordBegin,
-                        _ => LocationLabel::Simple
-                    };
+                    let label = self.word_boundaries.label(previous_character, character);
 
                     Location {
                         index: index,
                         character: character,
                         label: label,
                         height: self.height_map[&index],
-                        prefix: self.source[...index].to_owned()
+                        prefix: prefix.clone()
                     }
                 })
                 .or_else(|| {
@@ -103,7 +145,7 @@ impl<'a> Iterator for Locations<'a> {
                         character: character,
                         label: LocationLabel::WordBegin,
                         height: self.height_map[&index],
-                        prefix: self.source[...index].to_owned()
+                        prefix: prefix
                     })
                 });
             
@@ -122,12 +164,12 @@ mod tests {
 
     #[test]
     fn count() {
-        assert_eq!(4, Locations::new("ab").count());
+        assert_eq!(4, Locations::new("ab", &Options::default()).count());
     }
 
     #[test]
     fn simple() {
-        let locations: Vec<Location> = Locations::new("ab").collect();
+        let locations: Vec<Location> = Locations::new("ab", &Options::default()).collect();
 
         assert_eq!(locations[1].index, 0);
         assert_eq!(locations[1].character, 'a');
@@ -144,7 +186,7 @@ mod tests {
 
     #[test]
     fn underscore_word_begin() {
-        let locations: Vec<Location> = Locations::new("a_b").collect();
+        let locations: Vec<Location> = Locations::new("a_b", &Options::default()).collect();
 
         assert_eq!(locations[3].index, 2);
         assert_eq!(locations[3].character, 'b');
@@ -155,7 +197,7 @@ mod tests {
 
     #[test]
     fn hyphen_word_begin() {
-        let locations: Vec<Location> = Locations::new("a-b").collect();
+        let locations: Vec<Location> = Locations::new("a-b", &Options::default()).collect();
 
         assert_eq!(locations[3].index, 2);
         assert_eq!(locations[3].character, 'b');
@@ -166,7 +208,7 @@ mod tests {
 
     #[test]
     fn camel_word_begin() {
-        let locations: Vec<Location> = Locations::new("aB").collect();
+        let locations: Vec<Location> = Locations::new("aB", &Options::default()).collect();
 
         assert_eq!(locations[2].index, 1);
         assert_eq!(locations[2].character, 'B');
@@ -177,7 +219,7 @@ mod tests {
 
     #[test]
     fn slash_word_begin() {
-        let locations: Vec<Location> = Locations::new("a/b").collect();
+        let locations: Vec<Location> = Locations::new("a/b", &Options::default()).collect();
 
         assert_eq!(locations[3].index, 2);
         assert_eq!(locations[3].character, 'b');
@@ -185,4 +227,104 @@ mod tests {
         assert_eq!(locations[3].height, 0);
         assert_eq!(locations[3].prefix, "a/b");
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn multibyte_prefix() {
+        let locations: Vec<Location> = Locations::new("é/b", &Options::default()).collect();
+
+        assert_eq!(locations[1].index, 0);
+        assert_eq!(locations[1].prefix, "é");
+        assert_eq!(locations[1].height, 1);
+
+        assert_eq!(locations[3].index, 3);
+        assert_eq!(locations[3].character, 'b');
+        assert_eq!(locations[3].label, LocationLabel::WordBegin);
+        assert_eq!(locations[3].prefix, "é/b");
+    }
+
+    #[test]
+    fn normalized_locations() {
+        let locations: Vec<Location> = Locations::new("e\u{301}ß", &Options::default()).collect();
+
+        assert_eq!(5, locations.len());
+        assert_eq!(locations[1].character, 'é');
+        assert_eq!(locations[1].index, 0);
+        assert_eq!(locations[2].character, 's');
+        assert_eq!(locations[3].character, 's');
+        assert_eq!(locations[3].index, 3);
+        assert_eq!(locations[3].prefix, "e\u{301}ß");
+        assert_eq!(locations[4].index, 5);
+    }
+
+    fn labels(source: &str, word_boundaries: WordBoundaries) -> Vec<LocationLabel> {
+        let options = Options { word_boundaries: word_boundaries, ..Options::default() };
+
+        Locations::new(source, &options)
+            .skip(1)
+            .take(source.chars().count())
+            .map(|location| location.label)
+            .collect()
+    }
+
+    #[test]
+    fn dot_word_begin() {
+        let word_boundaries = WordBoundaries { characters: vec!['.'], digits: false };
+
+        assert_eq!(vec![
+            LocationLabel::WordBegin, LocationLabel::Simple, LocationLabel::WordBegin
+        ], labels("a.b", word_boundaries));
+
+        assert_eq!(LocationLabel::Simple, labels("a.b", WordBoundaries::default())[2]);
+    }
+
+    #[test]
+    fn space_word_begin() {
+        let word_boundaries = WordBoundaries { characters: vec![' '], digits: false };
+
+        assert_eq!(LocationLabel::WordBegin, labels("a b", word_boundaries)[2]);
+        assert_eq!(LocationLabel::Simple, labels("a b", WordBoundaries::default())[2]);
+    }
+
+    #[test]
+    fn backslash_word_begin() {
+        let word_boundaries = WordBoundaries { characters: vec!['\\'], digits: false };
+
+        assert_eq!(LocationLabel::WordBegin, labels("a\\b", word_boundaries)[2]);
+        assert_eq!(LocationLabel::Simple, labels("a\\b", WordBoundaries::default())[2]);
+    }
+
+    #[test]
+    fn digit_word_begin() {
+        let word_boundaries = WordBoundaries { characters: Vec::new(), digits: true };
+
+        assert_eq!(vec![
+            LocationLabel::WordBegin, LocationLabel::WordBegin, LocationLabel::Simple, LocationLabel::WordBegin
+        ], labels("v20b", word_boundaries));
+
+        assert_eq!(LocationLabel::Simple, labels("v20b", WordBoundaries::default())[1]);
+    }
+
+    #[test]
+    fn replaced_word_boundaries() {
+        let word_boundaries = WordBoundaries { characters: vec!['.'], digits: false };
+
+        assert_eq!(LocationLabel::Simple, labels("a_b", word_boundaries.clone())[2]);
+        assert_eq!(LocationLabel::WordBegin, labels("aB", word_boundaries)[1]);
+    }
+
+    #[test]
+    fn text_height() {
+        let options = Options { kind: Kind::Text, ..Options::default() };
+        let locations: Vec<Location> = Locations::new("a/b/c", &options).collect();
+
+        assert!(locations.iter().all(|location| location.height == 0));
+    }
+
+    #[test]
+    fn text_space_word_begin() {
+        let options = Options { kind: Kind::Text, ..Options::default() };
+        let locations: Vec<Location> = Locations::new("a b", &options).collect();
+
+        assert_eq!(locations[3].label, LocationLabel::WordBegin);
+    }
+}
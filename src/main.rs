@@ -2,88 +2,677 @@ extern crate termion;
 extern crate ff;
 extern crate libc;
 
-use std::io::{Write, stdin};
+use std::io::{Write, BufReader, stdin};
 use std::env;
+use std::sync::Arc;
+use std::sync::mpsc::{self, Sender, Receiver};
+use std::thread;
+use std::time::{Duration, Instant};
+use std::process::Command;
+use std::os::unix::process::CommandExt;
 
 use termion::raw::IntoRawMode;
-use termion::event::Key;
-use termion::input::TermRead;
-use termion::screen::AlternateScreen;
+use termion::event::{self, Key, MouseEvent, MouseButton};
+use termion::input::{TermRead, MouseTerminal};
+use termion::raw::RawTerminal;
+use termion::screen::{AlternateScreen, ToMainScreen, ToAlternateScreen};
 
+use ff::action::{Action, Bindings};
+use ff::fields::parse_ranges;
 use ff::index;
+use ff::normalize::Form;
+use ff::preview::{self, Preview, Previews};
+use ff::query::{CaseMatching, Mode};
+use ff::search::{Outcome, Search};
 use ff::ui::Screen;
-use ff::query_result::QueryResult;
+use ff::theme::{ColorChoice, Theme};
 
 use std::fs::File;
-use std::os::unix::io::IntoRawFd;
+use std::os::unix::io::{IntoRawFd, FromRawFd};
+
+struct Options {
+    case_matching: CaseMatching,
+    index: index::Options,
+    mode: Mode,
+    max_typos: usize,
+    preview: Option<preview::Options>,
+    bindings: Bindings,
+    source: Option<String>,
+    color: ColorChoice,
+    theme: Theme
+}
+
+// everything the main loop has to respond to
+enum Event {
+    Key(Key),
+    Mouse(MouseEvent),
+    Preview(Preview),
+    Resize,
+    Tick,
+    Indexed(Result<(index::Index, Vec<String>), String>),
+    Results(Outcome)
+}
+
+// mouse reporting has to be turned off while a command has the terminal
+static EXIT_MOUSE_SEQUENCE: &'static str = "\x1b[?1006l\x1b[?1015l\x1b[?1002l\x1b[?1000l";
+static ENTER_MOUSE_SEQUENCE: &'static str = "\x1b[?1000h\x1b[?1002h\x1b[?1015h\x1b[?1006h";
+
+// two clicks on the same row within this long accept it
+static DOUBLE_CLICK_MILLIS: u64 = 400;
+
+// the write end of the pipe the resize signal handler writes to
+static mut RESIZE_PIPE: libc::c_int = -1;
+
+extern "C" fn on_resize(_: libc::c_int) {
+    let byte = 0u8;
+
+    unsafe {
+        libc::write(RESIZE_PIPE, &byte as *const u8 as *const libc::c_void, 1);
+    }
+}
+
+impl Options {
+    fn from_args() -> Options {
+        let mut options = Options {
+            case_matching: CaseMatching::default(),
+            index: index::Options::default(),
+            mode: Mode::default(),
+            max_typos: 0,
+            preview: None,
+            bindings: Bindings::new(),
+            source: None,
+            color: ColorChoice::default(),
+            theme: Theme::default()
+        };
+
+        let mut preview_source: Option<preview::Source> = None;
+        let mut preview_position = preview::Position::default();
+        let mut preview_lines: Option<usize> = None;
+
+        let mut args = env::args().skip(1);
+
+        while let Some(arg) = args.next() {
+            match arg.as_ref() {
+                "-i" | "--ignore-case" => options.case_matching = CaseMatching::Insensitive,
+                "+i" | "--case-sensitive" => options.case_matching = CaseMatching::Sensitive,
+                "--smart-case" => options.case_matching = CaseMatching::Smart,
+                "--text" => options.index.kind = index::Kind::Text,
+                "--nfkc" => options.index.normalization.form = Form::Nfkc,
+                "--ignore-diacritics" => options.index.normalization.ignore_diacritics = true,
+                "--digit-boundaries" => options.index.word_boundaries.digits = true,
+                _ if arg.starts_with("--word-boundaries=") => {
+                    options.index.word_boundaries.characters = arg["--word-boundaries=".len()..].chars().collect();
+                },
+                _ if arg.starts_with("--delimiter=") => {
+                    options.index.fields.delimiter = Some(arg["--delimiter=".len()..].to_owned());
+                },
+                _ if arg.starts_with("--nth=") => {
+                    options.index.fields.nth = field_ranges(&arg, "--nth=");
+                },
+                _ if arg.starts_with("--with-nth=") => {
+                    options.index.fields.with_nth = field_ranges(&arg, "--with-nth=");
+                },
+                "--fuzzy" => options.mode = Mode::Fuzzy,
+                "--glob" => options.mode = Mode::Glob,
+                "--regex" => options.mode = Mode::Regex,
+                _ if arg.starts_with("--typos=") => {
+                    options.max_typos = arg["--typos=".len()..].parse().unwrap_or_else(|_| {
+                        writeln!(std::io::stderr(), "invalid number of typos: {}", arg).ok();
+                        std::process::exit(2);
+                    });
+                },
+                "--preview" => {
+                    preview_source = Some(preview::Source::Command(next_value(&mut args, &arg)));
+                },
+                _ if arg.starts_with("--preview=") => {
+                    preview_source = Some(preview::Source::Command(arg["--preview=".len()..].to_owned()));
+                },
+                "--preview-file" => preview_source = Some(preview::Source::File),
+                _ if arg.starts_with("--preview-lines=") => {
+                    preview_lines = Some(arg["--preview-lines=".len()..].parse().unwrap_or_else(|_| {
+                        writeln!(std::io::stderr(), "invalid number of preview lines: {}", arg).ok();
+                        std::process::exit(2);
+                    }));
+                },
+                "--preview-position=right" => preview_position = preview::Position::Right,
+                "--preview-position=bottom" => preview_position = preview::Position::Bottom,
+                "--bind" => {
+                    let binding = next_value(&mut args, &arg);
+                    bind(&mut options.bindings, &binding);
+                },
+                _ if arg.starts_with("--bind=") => {
+                    bind(&mut options.bindings, &arg["--bind=".len()..]);
+                },
+                "--source" => options.source = Some(next_value(&mut args, &arg)),
+                _ if arg.starts_with("--source=") => {
+                    options.source = Some(arg["--source=".len()..].to_owned());
+                },
+                _ if arg.starts_with("--color=") => {
+                    options.color = ColorChoice::parse(&arg["--color=".len()..]).unwrap_or_else(|| {
+                        writeln!(std::io::stderr(), "invalid color choice: {}", arg).ok();
+                        std::process::exit(2);
+                    });
+                },
+                _ if arg.starts_with("--theme=") => {
+                    options.theme = Theme::parse(&arg["--theme=".len()..]).unwrap_or_else(|| {
+                        writeln!(std::io::stderr(), "invalid theme: {}", arg).ok();
+                        std::process::exit(2);
+                    });
+                },
+                "--exec" => {
+                    options.bindings.bind(Key::Char('\n'), Action::Become(next_value(&mut args, &arg)));
+                },
+                _ if arg.starts_with("--exec=") => {
+                    options.bindings.bind(Key::Char('\n'), Action::Become(arg["--exec=".len()..].to_owned()));
+                },
+                _ => {
+                    writeln!(std::io::stderr(), "unknown option: {}", arg).ok();
+                    std::process::exit(2);
+                }
+            }
+        }
+
+        // asking for a number of lines is enough to get a preview of files
+        if preview_source.is_none() && preview_lines.is_some() {
+            preview_source = Some(preview::Source::File);
+        }
+
+        options.preview = preview_source.map(|source| {
+            let mut preview = preview::Options::new(source);
+            preview.position = preview_position;
+            preview_lines.map(|lines| preview.max_lines = lines);
+            preview
+        });
+
+        options
+    }
+}
+
+fn next_value<I: Iterator<Item = String>>(args: &mut I, arg: &str) -> String {
+    args.next().unwrap_or_else(|| {
+        writeln!(std::io::stderr(), "missing value for {}", arg).ok();
+        std::process::exit(2);
+    })
+}
+
+fn bind(bindings: &mut Bindings, binding: &str) {
+    bindings.parse(binding).unwrap_or_else(|| {
+        writeln!(std::io::stderr(), "invalid binding: {}", binding).ok();
+        std::process::exit(2);
+    });
+}
+
+fn field_ranges(arg: &str, prefix: &str) -> Vec<ff::fields::FieldRange> {
+    parse_ranges(&arg[prefix.len()..]).unwrap_or_else(|| {
+        writeln!(std::io::stderr(), "invalid field ranges: {}", arg).ok();
+        std::process::exit(2);
+    })
+}
 
 fn main() {
-    let index = build_index();
+    let options = Options::from_args();
+    // piped input is read in the background, from its own copy of stdin
+    // since stdin is about to become the terminal
+    let input = match termion::is_tty(&stdin()) {
+        true => None,
+        false => Some(unsafe { File::from_raw_fd(libc::dup(libc::STDIN_FILENO)) })
+    };
+
+    let can_reload = options.source.is_some() || input.is_none();
+
+    let index = index::Index::with_options(options.index.clone());
+    let mut errors: Vec<String> = Vec::new();
+
+    let mut query_string = String::new();
+    let mut mode = options.mode;
 
-    let mut query = index.query();
     let mut screen = Screen::new();
-    let mut output: Option<QueryResult> = None;
+    let mut output: Vec<String> = Vec::new();
+    let mut replacement: Option<String> = None;
 
     unsafe {
         let tty = File::open("/dev/tty").unwrap();
         libc::dup2(tty.into_raw_fd(), libc::STDIN_FILENO);
     }
 
+    options.preview.as_ref().map(|preview| screen.show_preview(preview.position));
+
+    let no_color = env::var("NO_COLOR").ok();
+    let term = env::var("TERM").ok();
+    screen.set_theme(options.color.theme(options.theme, no_color.as_ref().map(|s| s.as_ref()), term.as_ref().map(|s| s.as_ref())));
+
     {
-        let stdin = stdin();
-        let mut stdout = AlternateScreen::from(
+        let mut stdout = MouseTerminal::from(AlternateScreen::from(
             termion::get_tty().expect("get tty").into_raw_mode().expect("into raw mode")
-        );
+        ));
 
-        screen.current_query(&query);
+        let mut last_click: Option<(usize, Instant)> = None;
 
-        write!(stdout, "{}", screen).expect("failed to render screen");
-        stdout.flush().unwrap();
+        let (events, receiver) = mpsc::channel();
+        let mut requested_preview: Option<String> = None;
+        let previews = start_previews(&options, &events);
+        let resume = read_keys(events.clone());
+        watch_resize(events.clone());
+        tick(events.clone());
 
-        for c in stdin.keys() {
-            match c.unwrap() {
-                Key::Ctrl('c') => break,
-                Key::Char('\n') => {
-                    output = screen.selected;
-                    break
+        screen.set_loading(true);
+        load_index(&options, input, &events);
+
+        let mut search = start_search(index, &options, &events);
+        let mut searched = search.search(&query_string, mode, screen.result_rows());
+        let mut shown = 0;
+        screen.set_searching(true);
+
+        screen.draw(&mut stdout).expect("failed to render screen");
+
+        for event in receiver.iter() {
+            let key = match event {
+                Event::Key(key) => key,
+                Event::Mouse(mouse) => {
+                    if click(&mut screen, mouse, &mut last_click) {
+                        output = screen.accepted();
+                        break
+                    }
+
+                    load_preview(&screen, &previews, &mut requested_preview);
+                    screen.draw(&mut stdout).expect("failed to render screen");
+                    resume.send(()).ok();
+                    continue;
                 },
-                Key::Char(c) => {
-                    query.advance(c);
-                    screen.current_query(&query);
+                // a preview that was overtaken on its way is left out
+                Event::Preview(preview) => {
+                    if requested_preview.as_ref() != Some(&preview.path) {
+                        continue;
+                    }
+
+                    screen.set_preview(preview);
+                    screen.draw(&mut stdout).expect("failed to render screen");
+                    continue;
+                },
+                // results for an older query string are still shown, they
+                // are better than nothing until the latest ones arrive, but
+                // never in place of newer ones
+                Event::Results(outcome) => {
+                    if outcome.generation < shown {
+                        continue;
+                    }
+
+                    if outcome.generation == searched {
+                        screen.set_searching(false);
+                    }
+
+                    shown = outcome.generation;
+
+                    screen.show(outcome);
+                    load_preview(&screen, &previews, &mut requested_preview);
+                    screen.draw(&mut stdout).expect("failed to render screen");
+                    continue;
+                },
+                // there may be room for more results, or less
+                Event::Resize => {
+                    termion::terminal_size().map(|size| screen.set_size(size)).ok();
+                    searched = search.search(&query_string, mode, screen.result_rows());
+                    screen.set_searching(true);
+                    screen.draw(&mut stdout).expect("failed to render screen");
+                    continue;
+                },
+                // the spinner only moves while there is something to wait for
+                Event::Tick => {
+                    if !screen.is_busy() {
+                        continue;
+                    }
+
+                    screen.tick();
+                    screen.draw(&mut stdout).expect("failed to render screen");
+                    continue;
+                },
+                // the query string is kept, so the list changes underneath
+                // the prompt
+                Event::Indexed(Ok((index, unreadable))) => {
+                    search = start_search(index, &options, &events);
+                    searched = search.search(&query_string, mode, screen.result_rows());
+                    screen.set_loading(false);
+                    screen.set_error(unreadable_summary(&unreadable));
+                    errors.extend(unreadable);
+                    screen.set_searching(true);
+                    screen.draw(&mut stdout).expect("failed to render screen");
+                    continue;
                 },
-                Key::Backspace => {
-                    query.back();
-                    screen.current_query(&query);
+                // a failed reload leaves the old candidates in place
+                Event::Indexed(Err(error)) => {
+                    screen.set_error(Some(error.clone()));
+                    errors.push(error);
+                    screen.set_loading(false);
+                    screen.draw(&mut stdout).expect("failed to render screen");
+                    continue;
+                }
+            };
+
+            let (previous_query_string, previous_mode) = (query_string.clone(), mode);
+
+            match options.bindings.get(&key) {
+                Some(&Action::Accept) => {
+                    output = screen.accepted();
+                    break
                 },
-                Key::Down => {
-                    screen.move_selection_down();
+                Some(&Action::Abort) => break,
+                Some(&Action::Reload) => {
+                    if can_reload {
+                        screen.set_loading(true);
+                        load_index(&options, None, &events);
+                    }
                 },
-                Key::Up => {
-                    screen.move_selection_up();
+                Some(action @ &Action::Execute(_)) => {
+                    selected_command(&screen, action).map(|command| execute(&mut stdout, &command));
+                    screen.invalidate();
+                },
+                Some(action @ &Action::Become(_)) => {
+                    replacement = selected_command(&screen, action);
+
+                    if replacement.is_some() {
+                        break
+                    }
                 },
-                _ => println!("other")
+                None => match key {
+                    Key::Ctrl('c') => break,
+                    Key::Ctrl('r') => {
+                        mode = mode.next();
+                    },
+                    Key::Char('\n') => {
+                        output = screen.accepted();
+                        break
+                    },
+                    Key::Char('\t') => {
+                        screen.toggle_mark();
+                    },
+                    Key::Char(c) => {
+                        let cursor = screen.prompt_cursor();
+                        edit(&mut query_string, &mut screen, cursor, cursor, Some(c));
+                    },
+                    Key::Backspace => {
+                        let cursor = screen.prompt_cursor();
+
+                        if cursor > 0 {
+                            edit(&mut query_string, &mut screen, cursor - 1, cursor, None);
+                        }
+                    },
+                    Key::Delete => {
+                        let cursor = screen.prompt_cursor();
+
+                        if cursor < query_string.chars().count() {
+                            edit(&mut query_string, &mut screen, cursor, cursor + 1, None);
+                        }
+                    },
+                    Key::Left => {
+                        let cursor = screen.prompt_cursor();
+                        screen.set_prompt_cursor(cursor.saturating_sub(1));
+                    },
+                    Key::Right => {
+                        let cursor = screen.prompt_cursor();
+                        screen.set_prompt_cursor(cursor + 1);
+                    },
+                    Key::Home => {
+                        screen.set_prompt_cursor(0);
+                    },
+                    Key::End => {
+                        screen.set_prompt_cursor(usize::max_value());
+                    },
+                    Key::Down => {
+                        screen.move_selection_down();
+                    },
+                    Key::Up => {
+                        screen.move_selection_up();
+                    },
+                    Key::PageDown => {
+                        screen.scroll_preview_down();
+                    },
+                    Key::PageUp => {
+                        screen.scroll_preview_up();
+                    },
+                    // anything else isn't bound to anything
+                    _ => ()
+                }
+            }
+
+            // the key is done with as soon as the search is asked for, the
+            // results are drawn whenever they arrive
+            if query_string != previous_query_string || mode != previous_mode {
+                searched = search.search(&query_string, mode, screen.result_rows());
+                screen.set_searching(true);
             }
 
-            write!(stdout, "{}", screen).expect("failed to render screen");
-            stdout.flush().unwrap();
+            load_preview(&screen, &previews, &mut requested_preview);
+
+            screen.draw(&mut stdout).expect("failed to render screen");
+
+            resume.send(()).ok();
         }
 
         writeln!(stdout, "{}", termion::cursor::Show).expect("show the cursor");
     }
 
-    output.and_then(|result| {
-        writeln!(std::io::stdout(), "{}", result.path).ok()
-    });
+    // by now the terminal is back to normal, so the command can take over
+    if let Some(command) = replacement {
+        let err = Command::new("sh").arg("-c").arg(&command).exec();
+        writeln!(std::io::stderr(), "unable to run {}: {}", command, err).ok();
+        std::process::exit(1);
+    }
+
+    for error in errors {
+        writeln!(std::io::stderr(), "{}", error).ok();
+    }
+
+    for path in output {
+        writeln!(std::io::stdout(), "{}", path).ok();
+    }
 
     std::io::stdout().flush().expect("flush stdout");
 }
 
-fn build_index() -> index::Index {
-    let stdin = stdin();
-    if termion::is_tty(&stdin) {
-        let root = env::current_dir().expect("unable to get current dir");
-        return index::from_path(root);
-    } else {
-        return index::from_buf_reader(stdin.lock());
+// keys are read on their own thread so that the main loop can also be
+// woken up by work finishing in the background. Each key has to be handled
+// before the next is read, an executed command might need the terminal
+fn read_keys(events: Sender<Event>) -> Sender<()> {
+    let (resume, resumed): (Sender<()>, Receiver<()>) = mpsc::channel();
+
+    thread::spawn(move || {
+        for event in stdin().events() {
+            let sent = match event {
+                Ok(event::Event::Key(key)) => events.send(Event::Key(key)),
+                Ok(event::Event::Mouse(mouse)) => events.send(Event::Mouse(mouse)),
+                Ok(event::Event::Unsupported(_)) => continue,
+                // a resize can interrupt the read, that isn't a problem
+                Err(ref err) if err.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(_) => break
+            };
+
+            if sent.is_err() {
+                break;
+            }
+
+            if resumed.recv().is_err() {
+                break;
+            }
+        }
+    });
+
+    resume
+}
+
+// replaces the characters between start and end, and leaves the prompt
+// cursor after the edit
+fn edit<T: Clone>(query_string: &mut String, screen: &mut Screen<T>, start: usize, end: usize, c: Option<char>) {
+    let text: Vec<char> = query_string.chars().collect();
+
+    let mut edited: String = text[..start].iter().cloned().collect();
+    c.map(|c| edited.push(c));
+    let cursor = edited.chars().count();
+    edited.extend(text[end..].iter().cloned());
+
+    screen.set_query_string(&edited);
+    screen.set_prompt_cursor(cursor);
+    *query_string = edited;
+}
+
+// the index is handed over to the search, which answers with events
+fn start_search(index: index::Index, options: &Options, events: &Sender<Event>) -> Search {
+    let events = events.clone();
+
+    Search::start(Arc::new(index), options.case_matching, options.max_typos, move |outcome| {
+        events.send(Event::Results(outcome)).ok();
+    })
+}
+
+fn start_previews(options: &Options, events: &Sender<Event>) -> Option<Previews> {
+    let events = events.clone();
+
+    options.preview.clone().map(|options| Previews::start(options, move |preview| {
+        events.send(Event::Preview(preview)).ok();
+    }))
+}
+
+// a click selects the result under it, a second click on the same result
+// accepts it, and clicking on the prompt moves its cursor. Returns whether
+// the selection should be accepted
+fn click<T: Clone>(screen: &mut Screen<T>, mouse: MouseEvent, last_click: &mut Option<(usize, Instant)>) -> bool {
+    match mouse {
+        MouseEvent::Press(MouseButton::Left, x, 1) => screen.click_prompt(x),
+        MouseEvent::Press(MouseButton::Left, _, y) => {
+            let i = match screen.result_at(y) {
+                Some(i) => i,
+                None => return false
+            };
+
+            let now = Instant::now();
+            let double = match *last_click {
+                Some((clicked, at)) => clicked == i && now.duration_since(at) < Duration::from_millis(DOUBLE_CLICK_MILLIS),
+                None => false
+            };
+
+            screen.select(i);
+            *last_click = Some((i, now));
+
+            return double;
+        },
+        MouseEvent::Press(MouseButton::WheelUp, _, _) => screen.move_selection_up(),
+        MouseEvent::Press(MouseButton::WheelDown, _, _) => screen.move_selection_down(),
+        _ => ()
+    }
+
+    false
+}
+
+// signal handlers can't do much safely, so the handler only writes to a
+// pipe and a thread turns that into an event for the main loop
+fn watch_resize(events: Sender<Event>) {
+    let mut fds = [0 as libc::c_int; 2];
+
+    unsafe {
+        if libc::pipe(fds.as_mut_ptr()) != 0 {
+            return;
+        }
+
+        RESIZE_PIPE = fds[1];
+        libc::signal(libc::SIGWINCH, on_resize as *const () as libc::sighandler_t);
+    }
+
+    let read_end = fds[0];
+
+    thread::spawn(move || {
+        let mut byte = 0u8;
+
+        loop {
+            let read = unsafe { libc::read(read_end, &mut byte as *mut u8 as *mut libc::c_void, 1) };
+
+            match read {
+                1 => if events.send(Event::Resize).is_err() { break },
+                -1 if std::io::Error::last_os_error().kind() == std::io::ErrorKind::Interrupted => continue,
+                _ => break
+            }
+        }
+    });
+}
+
+fn selected_command<T: Clone>(screen: &Screen<T>, action: &Action) -> Option<String> {
+    action.command(&screen.accepted())
+}
+
+// the command gets the terminal as it was before ff started, until it exits
+fn execute(stdout: &mut MouseTerminal<AlternateScreen<RawTerminal<File>>>, command: &str) {
+    write!(stdout, "{}{}{}", EXIT_MOUSE_SEQUENCE, ToMainScreen, termion::cursor::Show).ok();
+    stdout.flush().ok();
+    stdout.suspend_raw_mode().ok();
+
+    let status = File::open("/dev/tty").and_then(|tty| {
+        Command::new("sh").arg("-c").arg(command).stdout(tty).status()
+    });
+
+    if let Err(err) = status {
+        writeln!(std::io::stderr(), "unable to run {}: {}", command, err).ok();
     }
+
+    stdout.activate_raw_mode().ok();
+    write!(stdout, "{}{}", ToAlternateScreen, ENTER_MOUSE_SEQUENCE).ok();
+}
+
+// a preview is only loaded once for each selected path, even if the
+// selection is still waiting for it to arrive
+fn load_preview<T: Clone>(screen: &Screen<T>, previews: &Option<Previews>, requested: &mut Option<String>) {
+    let (previews, path) = match (previews.as_ref(), screen.preview_path()) {
+        (Some(previews), Some(path)) => (previews, path),
+        _ => return
+    };
+
+    if requested.as_ref().map(|requested| requested.as_str()) == Some(path) {
+        return;
+    }
+
+    *requested = Some(path.to_owned());
+    previews.load(path);
+}
+
+// the candidates come from the source command, piped input or the current
+// directory, in that order. The index is built on its own thread, so the
+// screen can show a spinner in the meantime
+fn load_index(options: &Options, input: Option<File>, events: &Sender<Event>) {
+    let (source, index_options) = (options.source.clone(), options.index.clone());
+    let events = events.clone();
+
+    thread::spawn(move || {
+        let index = match (source, input) {
+            (Some(command), _) => index::from_command(&command, index_options)
+                .map(|index| (index, Vec::new()))
+                .map_err(|err| format!("unable to run {}: {}", command, err)),
+            (None, Some(input)) => index::from_buf_reader(BufReader::new(input), index_options)
+                .map(|index| (index, Vec::new()))
+                .map_err(|err| format!("unable to read the input: {}", err)),
+            (None, None) => env::current_dir()
+                .map(|root| index::from_path(root, index_options))
+                .map_err(|err| format!("unable to get current dir: {}", err))
+        };
+
+        events.send(Event::Indexed(index)).ok();
+    });
+}
+
+// the first of the paths that couldn't be read, and how many more there are
+fn unreadable_summary(unreadable: &[String]) -> Option<String> {
+    match unreadable.len() {
+        0 => None,
+        1 => Some(unreadable[0].clone()),
+        n => Some(format!("{} (and {} more)", unreadable[0], n - 1))
+    }
+}
+
+// ticks for as long as ff runs, the spinner ignores them when it is idle
+fn tick(events: Sender<Event>) {
+    thread::spawn(move || {
+        loop {
+            thread::sleep(Duration::from_millis(100));
+
+            if events.send(Event::Tick).is_err() {
+                break;
+            }
+        }
+    });
 }
\ No newline at end of file
@@ -28,6 +28,12 @@ impl<T: Ord + Hash + Eq> MinSet<T> {
             }
         }
     }
+
+    pub fn extend(&mut self, other: MinSet<T>) {
+        for element in other {
+            self.insert(element);
+        }
+    }
 }
 
 impl<T: Ord + Hash + Eq> IntoIterator for MinSet<T> {
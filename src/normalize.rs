@@ -0,0 +1,206 @@
+use unicode_normalization::UnicodeNormalization;
+use unicode_normalization::char::{canonical_combining_class, compose, is_combining_mark};
+use caseless::Caseless;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Form {
+    Nfc,
+    Nfkc
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Normalization {
+    pub form: Form,
+    pub ignore_diacritics: bool
+}
+
+impl Default for Normalization {
+    fn default() -> Normalization {
+        Normalization {
+            form: Form::Nfc,
+            ignore_diacritics: false
+        }
+    }
+}
+
+// a single character of the normalised string, along with where it came
+// from in the original string and the graph node it leads to
+#[derive(Debug, PartialEq)]
+pub struct Unit {
+    pub index: usize,
+    pub character: char,
+    pub prefix: String
+}
+
+impl Normalization {
+    pub fn units(&self, source: &str) -> Vec<Unit> {
+        let mut units = Vec::new();
+
+        for (start, end) in clusters(source) {
+            let characters = self.normalize(&source[start..end]);
+            let count = characters.len();
+
+            for (i, &character) in characters.iter().enumerate() {
+                // every unit needs its own node, a character that expands
+                // into several (ß => ss) gets nodes which can't collide with
+                // a real prefix for all but its last unit
+                let prefix = if i + 1 == count {
+                    source[..end].to_owned()
+                } else {
+                    let expanded: String = characters[..i + 1].iter().cloned().collect();
+                    format!("{}\0{}", &source[..start], expanded)
+                };
+
+                units.push(Unit {
+                    index: start,
+                    character: character,
+                    prefix: prefix
+                });
+            }
+        }
+
+        units
+    }
+
+    // the characters a typed cluster should be matched as
+    pub fn characters(&self, cluster: &str) -> Vec<char> {
+        self.normalize(cluster)
+    }
+
+    fn normalize(&self, s: &str) -> Vec<char> {
+        let composed: Vec<char> = match (self.form, self.ignore_diacritics) {
+            (Form::Nfc, false) => s.nfc().collect(),
+            (Form::Nfkc, false) => s.nfkc().collect(),
+            (Form::Nfc, true) => s.nfd().filter(|&c| !is_combining_mark(c)).nfc().collect(),
+            (Form::Nfkc, true) => s.nfkd().filter(|&c| !is_combining_mark(c)).nfkc().collect()
+        };
+
+        let mut characters = Vec::new();
+
+        for c in composed {
+            let folded: Vec<char> = Some(c).into_iter().default_case_fold().collect();
+
+            // case is otherwise left alone so that the query can decide how
+            // to match it, only folds that change the length are applied
+            if folded.len() > 1 {
+                characters.extend(folded);
+            } else {
+                characters.push(c);
+            }
+        }
+
+        characters
+    }
+}
+
+// byte ranges that normalisation never needs to look past, each starts
+// with a character that can't combine with anything before it. Combining
+// marks always belong to the cluster before them, and so does anything
+// that composes with it, like the jamo of a decomposed hangul syllable
+fn clusters(source: &str) -> Vec<(usize, usize)> {
+    let mut clusters: Vec<(usize, usize)> = Vec::new();
+
+    for (index, c) in source.char_indices() {
+        let end = index + c.len_utf8();
+
+        match clusters.last_mut() {
+            Some(cluster) if continues(&source[cluster.0..cluster.1], c) => cluster.1 = end,
+            _ => clusters.push((index, end))
+        }
+    }
+
+    clusters
+}
+
+// where the last cluster of a query starts, a typed character that
+// continues it has to be matched along with the rest of it
+pub fn last_cluster(s: &str) -> usize {
+    clusters(s).last().map(|cluster| cluster.0).unwrap_or(0)
+}
+
+fn continues(cluster: &str, c: char) -> bool {
+    if is_combining_mark(c) || canonical_combining_class(c) != 0 {
+        return true;
+    }
+
+    cluster.nfc().last().and_then(|last| compose(last, c)).is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn characters(normalization: Normalization, s: &str) -> String {
+        normalization.units(s).into_iter().map(|unit| unit.character).collect()
+    }
+
+    #[test]
+    fn ascii_is_unchanged() {
+        let units = Normalization::default().units("ab");
+
+        assert_eq!(vec![
+            Unit { index: 0, character: 'a', prefix: String::from("a") },
+            Unit { index: 1, character: 'b', prefix: String::from("ab") }
+        ], units);
+    }
+
+    #[test]
+    fn decomposed_characters_are_composed() {
+        let units = Normalization::default().units("cafe\u{301}/x");
+
+        assert_eq!("café/x", units.iter().map(|unit| unit.character).collect::<String>());
+        assert_eq!(3, units[3].index);
+        assert_eq!("cafe\u{301}", units[3].prefix);
+        assert_eq!(6, units[4].index);
+    }
+
+    #[test]
+    fn decomposed_hangul_is_composed() {
+        let units = Normalization::default().units("\u{1112}\u{1161}\u{11AB}/x");
+
+        assert_eq!("한/x", units.iter().map(|unit| unit.character).collect::<String>());
+        assert_eq!(0, units[0].index);
+        assert_eq!(9, units[1].index);
+    }
+
+    #[test]
+    fn last_clusters() {
+        assert_eq!(3, last_cluster("cafe\u{301}"));
+        assert_eq!(3, last_cluster("cafe"));
+        assert_eq!(0, last_cluster("\u{1112}\u{1161}"));
+        assert_eq!(0, last_cluster(""));
+    }
+
+    #[test]
+    fn compatibility_characters() {
+        let nfkc = Normalization { form: Form::Nfkc, ignore_diacritics: false };
+
+        assert_eq!("Ａb", characters(Normalization::default(), "Ａb"));
+        assert_eq!("Ab", characters(nfkc, "Ａb"));
+    }
+
+    #[test]
+    fn expanding_case_folds() {
+        let units = Normalization::default().units("aß");
+
+        assert_eq!("ass", units.iter().map(|unit| unit.character).collect::<String>());
+        assert_eq!(1, units[1].index);
+        assert_eq!(1, units[2].index);
+        assert_eq!("a\0s", units[1].prefix);
+        assert_eq!("aß", units[2].prefix);
+    }
+
+    #[test]
+    fn case_is_preserved() {
+        assert_eq!("AbC", characters(Normalization::default(), "AbC"));
+    }
+
+    #[test]
+    fn ignore_diacritics() {
+        let normalization = Normalization { form: Form::Nfc, ignore_diacritics: true };
+
+        assert_eq!("cafe", characters(normalization, "café"));
+        assert_eq!("cafe", characters(normalization, "cafe\u{301}"));
+        assert_eq!(vec!['e'], normalization.characters("é"));
+    }
+}
@@ -0,0 +1,336 @@
+use std::fs::File;
+use std::io::{self, Read};
+use std::str::Chars;
+use std::process::{Command, Stdio};
+use std::sync::mpsc::{self, Sender, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::Duration;
+
+use latest::{Jobs, Latest};
+use shell;
+
+// only the start of a file is ever shown, so there is no need to read more
+static MAX_BYTES: u64 = 64 * 1024;
+
+// how often a running preview command checks whether it is still wanted
+static POLL_MILLIS: u64 = 10;
+
+static TAB_WIDTH: usize = 4;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Source {
+    File,
+    Command(String)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Position {
+    Right,
+    Bottom
+}
+
+impl Default for Position {
+    fn default() -> Position {
+        Position::Right
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Options {
+    pub source: Source,
+    pub position: Position,
+    pub max_lines: usize
+}
+
+impl Options {
+    pub fn new(source: Source) -> Options {
+        Options {
+            source: source,
+            position: Position::default(),
+            max_lines: 500
+        }
+    }
+}
+
+// the loaded lines for a single path, along with how far it has been scrolled
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Preview {
+    pub path: String,
+    pub lines: Vec<String>,
+    pub offset: usize
+}
+
+impl Preview {
+    pub fn new(path: &str, lines: Vec<String>) -> Preview {
+        Preview {
+            path: path.to_owned(),
+            lines: lines,
+            offset: 0
+        }
+    }
+
+    // this can be slow, a file could be on a network drive or the command
+    // could take a while, so it is expected to be called off the main thread
+    pub fn load(options: &Options, path: &str) -> Preview {
+        Preview::load_unless(options, path, &|| false).expect("a preview that can't be cancelled loads")
+    }
+
+    // a command that is still running when the preview is cancelled is
+    // killed, and nothing is returned
+    pub fn load_unless<C: Fn() -> bool>(options: &Options, path: &str, cancelled: &C) -> Option<Preview> {
+        let bytes = match options.source {
+            Source::File => read_file(path),
+            Source::Command(ref template) => match run_command(&shell::substitute(template, path), cancelled) {
+                Some(bytes) => bytes,
+                None => return None
+            }
+        };
+
+        let lines = match bytes {
+            Ok(ref bytes) if is_binary(bytes) => vec![String::from("[binary file]")],
+            Ok(bytes) => printable_lines(&bytes, options.max_lines),
+            Err(message) => vec![message]
+        };
+
+        Some(Preview::new(path, lines))
+    }
+
+    pub fn scroll_down(&mut self, lines: usize) {
+        let last = self.lines.len().saturating_sub(1);
+        self.offset = (self.offset + lines).min(last);
+    }
+
+    pub fn scroll_up(&mut self, lines: usize) {
+        self.offset = self.offset.saturating_sub(lines);
+    }
+
+    pub fn visible_lines(&self, height: usize) -> &[String] {
+        let end = (self.offset + height).min(self.lines.len());
+        &self.lines[self.offset.min(end)..end]
+    }
+}
+
+fn read_file(path: &str) -> Result<Vec<u8>, String> {
+    let mut bytes = Vec::new();
+
+    File::open(path)
+        .and_then(|file| file.take(MAX_BYTES).read_to_end(&mut bytes))
+        .map(|_| bytes)
+        .map_err(|err| format!("unable to read {}: {}", path, err))
+}
+
+// the output is read on a thread of its own, so that the command can be
+// killed while it is still writing
+fn run_command<C: Fn() -> bool>(command: &str, cancelled: &C) -> Option<Result<Vec<u8>, String>> {
+    let child = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn();
+
+    let mut child = match child {
+        Ok(child) => child,
+        Err(err) => return Some(Err(format!("unable to run {}: {}", command, err)))
+    };
+
+    let mut stdout = child.stdout.take().expect("the output of the preview command");
+    let (sender, output): (Sender<io::Result<Vec<u8>>>, Receiver<io::Result<Vec<u8>>>) = mpsc::channel();
+
+    thread::spawn(move || {
+        let mut bytes = Vec::new();
+        sender.send(stdout.read_to_end(&mut bytes).map(|_| bytes)).ok();
+    });
+
+    loop {
+        match output.recv_timeout(Duration::from_millis(POLL_MILLIS)) {
+            Ok(bytes) => {
+                child.wait().ok();
+                return Some(bytes.map_err(|err| format!("unable to run {}: {}", command, err)));
+            },
+            Err(RecvTimeoutError::Timeout) => if cancelled() {
+                child.kill().ok();
+                child.wait().ok();
+                return None;
+            },
+            Err(RecvTimeoutError::Disconnected) => {
+                child.wait().ok();
+                return Some(Ok(Vec::new()));
+            }
+        }
+    }
+}
+
+// previews loaded one at a time on their own thread, a command still
+// running for a path that is no longer selected is killed and only the
+// latest preview is sent on
+pub struct Previews {
+    requests: Latest<String>
+}
+
+impl Previews {
+    pub fn start<F>(options: Options, respond: F) -> Previews
+        where F: Fn(Preview) + Send + 'static
+    {
+        let requests = Latest::start(move |jobs: Jobs<String>| {
+            for job in jobs {
+                let cancelled = || job.cancelled();
+
+                match Preview::load_unless(&options, &job.request, &cancelled) {
+                    Some(preview) => if !cancelled() {
+                        respond(preview);
+                    },
+                    None => ()
+                }
+            }
+        });
+
+        Previews {
+            requests: requests
+        }
+    }
+
+    pub fn load(&self, path: &str) {
+        self.requests.push(path.to_owned());
+    }
+}
+
+// the same check as git and grep, a nul byte in the first block of a file
+// almost never happens in text
+fn is_binary(bytes: &[u8]) -> bool {
+    bytes.iter().take(8000).any(|&b| b == 0)
+}
+
+// anything that could move the cursor or change the terminal's state is
+// dropped, escape sequences as a whole, so only plain text ends up on the
+// screen. Colours from commands like `bat --color=always` go too
+fn printable_lines(bytes: &[u8], max_lines: usize) -> Vec<String> {
+    String::from_utf8_lossy(bytes)
+        .lines()
+        .take(max_lines)
+        .map(|line| {
+            let mut printable = String::new();
+            let mut chars = line.chars();
+
+            while let Some(c) = chars.next() {
+                match c {
+                    '\t' => printable.push_str(&" ".repeat(TAB_WIDTH)),
+                    _ if c == ESCAPE => skip_escape(&mut chars),
+                    _ if c.is_control() => (),
+                    _ => printable.push(c)
+                }
+            }
+
+            printable
+        })
+        .collect()
+}
+
+static ESCAPE: char = '\u{1B}';
+
+static BELL: char = '\u{7}';
+
+// whatever follows an escape up to the end of its sequence. A control
+// sequence, `ESC [`, ends with a byte from `@` to `~`, an operating system
+// command, `ESC ]`, with a bell or `ESC \`. Anything else is the escape and
+// one more character, after any intermediate ones
+fn skip_escape(chars: &mut Chars) {
+    match chars.next() {
+        Some('[') => {
+            while let Some(c) = chars.next() {
+                if c >= '@' && c <= '~' {
+                    break;
+                }
+            }
+        },
+        Some(']') => {
+            while let Some(c) = chars.next() {
+                if c == BELL || (c == ESCAPE && chars.next() == Some('\\')) {
+                    break;
+                }
+            }
+        },
+        Some(c) if c >= ' ' && c <= '/' => {
+            chars.next();
+        },
+        _ => ()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::time::Instant;
+
+    #[test]
+    fn binary_detection() {
+        assert!(is_binary(b"\x7fELF\x00\x01"));
+        assert!(!is_binary(b"fn main() {}\n"));
+    }
+
+    #[test]
+    fn control_characters_are_removed() {
+        let lines = printable_lines(b"\x1b[2Jclear\r\n\tindented\nlast", 2);
+
+        assert_eq!(vec!["clear", "    indented"], lines);
+    }
+
+    #[test]
+    fn escape_sequences_are_removed() {
+        let coloured = b"\x1b[38;5;231mfn\x1b[0m main\n\x1b]0;title\x07a\x1b]8;;http://x\x1b\\link\x1b(Bb";
+
+        assert_eq!(vec!["fn main", "alinkb"], printable_lines(coloured, 10));
+    }
+
+    #[test]
+    fn command_preview() {
+        let options = Options::new(Source::Command(String::from("echo {}; echo second")));
+        let preview = Preview::load(&options, "it's a path");
+
+        assert_eq!(vec!["it's a path", "second"], preview.lines);
+    }
+
+    #[test]
+    fn cancelled_commands_are_killed() {
+        let options = Options::new(Source::Command(String::from("sleep 10")));
+        let start = Instant::now();
+
+        assert_eq!(None, Preview::load_unless(&options, "path", &|| start.elapsed() > Duration::from_millis(50)));
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn only_the_latest_preview_arrives() {
+        let (sender, receiver) = mpsc::channel();
+        let previews = Previews::start(Options::new(Source::Command(String::from("sleep 0.2; echo {}"))), move |preview| {
+            sender.send(preview).ok();
+        });
+
+        for path in &["a", "b", "c"] {
+            previews.load(path);
+        }
+
+        let preview = receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+
+        assert_eq!("c", preview.path);
+        assert_eq!(vec!["c"], preview.lines);
+        assert!(receiver.recv_timeout(Duration::from_millis(500)).is_err());
+    }
+
+    #[test]
+    fn scrolling() {
+        let lines = (0..5).map(|i| i.to_string()).collect();
+        let mut preview = Preview::new("path", lines);
+
+        preview.scroll_down(3);
+        assert_eq!(&["3", "4"], preview.visible_lines(3));
+
+        preview.scroll_down(10);
+        assert_eq!(4, preview.offset);
+
+        preview.scroll_up(10);
+        assert_eq!(&["0", "1"], preview.visible_lines(2));
+    }
+}
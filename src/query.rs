@@ -1,227 +1,451 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::cmp::{Eq, PartialEq, PartialOrd, Ord, Ordering};
 use std::hash::{Hash, Hasher};
 use std::fmt;
+use std::mem;
 
-use index::{Graph, Node, Edge};
+use filter::Filter;
+use index::Index;
 use query_result::QueryResults;
-use min_set::MinSet;
+use syntax::{self, Clause, Pattern};
+use term::Term;
+
+pub use term::CaseMatching;
 
 #[derive(Debug)]
-struct Cursor {
-    node: Node,
-    score: usize,
-    positions: Vec<usize>
+pub struct Match {
+    pub path: String,
+    pub score: usize,
+    pub positions: Vec<usize>
 }
 
-impl PartialOrd for Cursor {
-    fn partial_cmp(&self, other: &Cursor) -> Option<Ordering> {
+impl PartialOrd for Match {
+    fn partial_cmp(&self, other: &Match) -> Option<Ordering> {
         Some(self.cmp(other))
     }
 }
 
-impl Ord for Cursor {
-    fn cmp(&self, other: &Cursor) -> Ordering {
-        self.score.cmp(&other.score)
+impl Ord for Match {
+    fn cmp(&self, other: &Match) -> Ordering {
+        self.score.cmp(&other.score).then_with(|| self.positions.cmp(&other.positions))
     }
 }
 
-impl Hash for Cursor {
+impl Hash for Match {
     fn hash<H: Hasher>(&self, state: &mut H) {
-        self.node.hash(state);
+        self.path.hash(state);
     }
 }
 
-impl PartialEq for Cursor {
-    fn eq(&self, other: &Cursor) -> bool {
-        self.node == other.node
+impl PartialEq for Match {
+    fn eq(&self, other: &Match) -> bool {
+        self.path == other.path
     }
 }
 
-impl Eq for Cursor {
+impl Eq for Match {
 
 }
 
-impl Cursor {
-    fn extend(&self, edge: &Edge) -> Cursor {
-        let mut positions = self.positions.clone();
-        positions.push(edge.position);
+impl Match {
+    // a path only matches the whole query when it matches every clause, its
+    // score is the total of the best path through the graph for each one
+    fn combine(&mut self, other: Match) {
+        self.score = self.score + other.score;
+        self.positions.extend(other.positions);
+        self.positions.sort();
+        self.positions.dedup();
+    }
+}
 
-        Cursor {
-            node: edge.path.to_owned(),
-            score: self.score + edge.score,
-            positions: positions
+// a space separated token of the query string along with the search for
+// the pattern it contains
+struct Token<'a> {
+    text: String,
+    pattern: Pattern,
+    term: Term<'a>
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    Fuzzy,
+    Glob,
+    Regex
+}
+
+impl Default for Mode {
+    fn default() -> Mode {
+        Mode::Fuzzy
+    }
+}
+
+impl Mode {
+    pub fn next(&self) -> Mode {
+        match *self {
+            Mode::Fuzzy => Mode::Glob,
+            Mode::Glob => Mode::Regex,
+            Mode::Regex => Mode::Fuzzy
         }
     }
 }
 
-#[derive(Debug)]
-struct Step {
-    character: char,
-    cursors: HashSet<Cursor>
+impl fmt::Display for Mode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Mode::Fuzzy => write!(f, "fuzzy"),
+            Mode::Glob => write!(f, "glob"),
+            Mode::Regex => write!(f, "regex")
+        }
+    }
+}
+
+// in fuzzy mode the query string is split into terms on spaces, each term is
+// matched independently so they can appear anywhere, and in any order, in
+// the path. The other modes match the whole query string against each path
+pub struct Query<'a, T: 'a = ()> {
+    index: &'a Index<T>,
+    case_matching: CaseMatching,
+    max_typos: usize,
+    threads: usize,
+    mode: Mode,
+    text: String,
+    tokens: Vec<Token<'a>>
 }
 
-impl Step {
-    fn first() -> Step {
-        let cursor = Cursor {
-            node: String::from(""),
-            score: 0,
-            positions: Vec::new()
+impl<'a, T: Clone> Query<'a, T> {
+    pub fn new(index: &'a Index<T>, case_matching: CaseMatching) -> Query<'a, T> {
+        let mut query = Query {
+            index: index,
+            case_matching: case_matching,
+            max_typos: 0,
+            threads: 0,
+            mode: Mode::default(),
+            text: String::new(),
+            tokens: Vec::new()
         };
 
-        let mut cursors = HashSet::new();
-        cursors.insert(cursor);
+        query.push_token();
+        query
+    }
+
+    pub fn results(&self) -> QueryResults<T> {
+        let mut results = QueryResults::new();
+
+        for m in self.matches() {
+            results.extend(self.index.results(&m));
+        }
 
-        Step {
-            character: '^',
-            cursors: cursors
+        results
+    }
+
+    // every key that matches, with the positions of the characters it
+    // matched in the order they were matched
+    pub fn matches(&self) -> Vec<Match> {
+        self.matches_unless(&|| false).expect("matches that can't be cancelled are all found")
+    }
+
+    // the same, but gives up part of the way through the search once it is
+    // cancelled
+    pub fn matches_unless<C: Fn() -> bool + Sync>(&self, cancelled: &C) -> Option<Vec<Match>> {
+        match self.mode {
+            Mode::Fuzzy => self.fuzzy_matches(cancelled),
+            Mode::Glob | Mode::Regex => self.filter_matches(cancelled)
         }
     }
 
-    fn new(character: char) -> Step {
-        Step {
-            character: character,
-            cursors: HashSet::new()
+    pub fn advance(&mut self, character: char) {
+        self.text.push(character);
+
+        if self.mode == Mode::Fuzzy {
+            self.advance_tokens(character);
         }
     }
 
-    fn push(&mut self, cursor: Cursor) {
-        match self.cursors.take(&cursor) {
-            Some(ref existing) if existing > &cursor => {
-                self.cursors.insert(cursor);
-            },
+    pub fn back(&mut self) {
+        if self.text.pop().is_none() {
+            return;
+        }
+
+        if self.mode == Mode::Fuzzy {
+            self.back_tokens();
+        }
+    }
 
-            Some(existing) => {
-                self.cursors.insert(existing);
+    // only the part of the query after the first change is searched again,
+    // so editing the middle of the query costs the same as backspacing to it
+    pub fn set_query_string(&mut self, query_string: &str) {
+        self.set_query_string_unless(query_string, || false);
+    }
+
+    // the same, but gives up between characters once it is cancelled,
+    // leaving the query at whatever it had got to. Returns whether the whole
+    // query string was set
+    pub fn set_query_string_unless<F: Fn() -> bool>(&mut self, query_string: &str, cancelled: F) -> bool {
+        let common = self.text.chars()
+            .zip(query_string.chars())
+            .take_while(|&(a, b)| a == b)
+            .count();
+
+        while self.text.chars().count() > common {
+            if cancelled() {
+                return false;
             }
 
-            None => {
-                self.cursors.insert(cursor);
+            self.back();
+        }
+
+        for c in query_string.chars().skip(common) {
+            if cancelled() {
+                return false;
             }
+
+            self.advance(c);
         }
+
+        true
     }
-}
 
-#[derive(Debug)]
-pub struct Match {
-    pub path: String,
-    pub score: usize,
-    pub positions: Vec<usize>
-}
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
 
-impl From<Cursor> for Match {
-    fn from(cursor: Cursor) -> Match {
-        Match {
-            path: cursor.node,
-            score: cursor.score,
-            positions: cursor.positions
-        }
+    // the fuzzy search is only kept up to date while it is being used
+    pub fn set_mode(&mut self, mode: Mode) {
+        self.set_mode_unless(mode, || false);
     }
-}
 
-impl PartialOrd for Match {
-    fn partial_cmp(&self, other: &Match) -> Option<Ordering> {
-        Some(self.cmp(other))
+    // the same, but the fuzzy search is only rebuilt for as much of the
+    // query string as it gets through before being cancelled, and the query
+    // string is cut back to match. Returns whether all of it was rebuilt
+    pub fn set_mode_unless<F: Fn() -> bool>(&mut self, mode: Mode, cancelled: F) -> bool {
+        let rebuild = mode == Mode::Fuzzy && self.mode != Mode::Fuzzy;
+
+        self.mode = mode;
+
+        !rebuild || self.rebuild_tokens_unless(cancelled)
     }
-}
 
-impl Ord for Match {
-    fn cmp(&self, other: &Match) -> Ordering {
-        self.score.cmp(&other.score)
+    pub fn max_typos(&self) -> usize {
+        self.max_typos
     }
-}
 
-impl Hash for Match {
-    fn hash<H: Hasher>(&self, state: &mut H) {
-        self.path.hash(state);
+    // allowing typos lets a fuzzy term survive a mistyped, extra or swapped
+    // character, at a cost to its score
+    pub fn set_max_typos(&mut self, max_typos: usize) {
+        self.max_typos = max_typos;
+
+        if self.mode == Mode::Fuzzy {
+            self.rebuild_tokens();
+        }
     }
-}
 
-impl PartialEq for Match {
-    fn eq(&self, other: &Match) -> bool {
-        self.path == other.path
+    pub fn threads(&self) -> usize {
+        self.threads
     }
-}
 
-impl Eq for Match {
+    // big searches are spread over this many threads, or one for each
+    // processor when it is zero. The results are the same either way
+    pub fn set_threads(&mut self, threads: usize) {
+        self.threads = threads;
 
-}
+        if self.mode == Mode::Fuzzy {
+            self.rebuild_tokens();
+        }
+    }
 
-pub struct Query<'a> {
-    graph: &'a Graph,
-    steps: Vec<Step>
-}
+    pub fn case_matching(&self) -> CaseMatching {
+        self.case_matching
+    }
+
+    pub fn index(&self) -> &'a Index<T> {
+        self.index
+    }
+
+    pub fn query_string(&self) -> String {
+        self.text.clone()
+    }
 
-impl<'a> Query<'a> {
-    pub fn new(graph: &'a Graph) -> Query<'a> {
-        let step = Step::first();
+    fn filter_matches<C: Fn() -> bool + Sync>(&self, cancelled: &C) -> Option<Vec<Match>> {
+        let case_insensitive = !self.case_matching.is_sensitive(&self.text);
 
-        Query {
-            graph: graph,
-            steps: vec![step]
+        if self.text.is_empty() {
+            return self.all_matches(cancelled).map(|matches| matches.into_iter().map(|(_, m)| m).collect());
         }
+
+        let filter = match self.mode {
+            Mode::Glob => Filter::glob(&self.text, case_insensitive),
+            _ => Filter::regex(&self.text, case_insensitive)
+        };
+
+        // an invalid pattern, probably because it is still being typed,
+        // just doesn't match anything
+        Some(filter.map(|filter| filter.matches(self.index.keys())).unwrap_or_default())
     }
 
-    pub fn results(&self) -> QueryResults {
-        let mut match_set: MinSet<Match> = MinSet::new();
+    fn fuzzy_matches<C: Fn() -> bool + Sync>(&self, cancelled: &C) -> Option<Vec<Match>> {
+        let mut matches: Option<HashMap<String, Match>> = None;
 
-        let mut results = QueryResults::new();
+        for clause in syntax::parse(&self.query_string()) {
+            let mut clause_matches = self.clause_matches(&clause, cancelled)?;
+
+            if cancelled() {
+                return None;
+            }
+
+            matches = match matches {
+                None => Some(clause_matches),
+                Some(matches) => Some(matches.into_iter()
+                    .filter_map(|(path, mut m)| {
+                        clause_matches.remove(&path).map(|other| {
+                            m.combine(other);
+                            (path, m)
+                        })
+                    })
+                    .collect())
+            };
+        }
 
-        for cursor in &self.current_step().cursors {
-            let edge_map = &self.graph[&cursor.node];
+        // an empty query still has to match everything
+        let matches = match matches {
+            Some(matches) => matches,
+            None => self.all_matches(cancelled)?
+        };
 
-            edge_map.get(&'$').and_then(|edges| {
-                for edge in edges {
-                    match_set.insert(cursor.extend(edge).into())
-                };
+        Some(matches.into_iter().map(|(_, m)| m).collect())
+    }
 
-                Some(())
-            });
+    fn advance_tokens(&mut self, character: char) {
+        if character == ' ' {
+            self.push_token();
+            return;
         }
 
-        for m in match_set.into_iter() {
-            results.insert(m.into());
+        let mut text = self.current_token().text.clone();
+        text.push(character);
+
+        self.update_token(text);
+    }
+
+    fn back_tokens(&mut self) {
+        let mut text = self.current_token().text.clone();
+
+        if text.pop().is_none() {
+            if self.tokens.len() > 1 {
+                self.tokens.pop();
+            }
+
+            return;
         }
 
-        return results;
+        self.update_token(text);
     }
 
-    pub fn advance(&mut self, character: char) {
-        let mut next_step = Step::new(character);
+    fn current_token(&self) -> &Token<'a> {
+        self.tokens.last().expect("it should be impossible to have no tokens")
+    }
+
+    fn rebuild_tokens(&mut self) {
+        self.rebuild_tokens_unless(|| false);
+    }
 
-        for cursor in &self.current_step().cursors {
-            let edge_map = &self.graph[&cursor.node];
+    fn rebuild_tokens_unless<F: Fn() -> bool>(&mut self, cancelled: F) -> bool {
+        let text = mem::replace(&mut self.text, String::new());
 
-            edge_map.get(&character).and_then(|edges| {
-                for edge in edges {
-                    next_step.push(cursor.extend(&edge));
-                };
+        self.tokens.clear();
+        self.push_token();
 
-                Some(()) // we just have to return _something_
-            });
+        for character in text.chars() {
+            if cancelled() {
+                return false;
+            }
+
+            self.text.push(character);
+            self.advance_tokens(character);
         }
 
-        self.steps.push(next_step);
+        true
     }
 
-    pub fn back(&mut self) {
-        if self.steps.len() > 1 {
-            self.steps.pop();
+    fn push_token(&mut self) {
+        let pattern = Pattern::parse(self.tokens.len(), "");
+        let term = Term::new(self.index, self.case_matching, self.max_typos, self.threads, &pattern);
+
+        self.tokens.push(Token {
+            text: String::new(),
+            pattern: pattern,
+            term: term
+        });
+    }
+
+    // the search for the current token is only extended, or shortened, if
+    // the edit didn't change any operators, otherwise it has to start again
+    fn update_token(&mut self, text: String) {
+        let (index, case_matching, max_typos, threads) = (self.index, self.case_matching, self.max_typos, self.threads);
+        let token = self.tokens.last_mut().expect("it should be impossible to have no tokens");
+        let pattern = Pattern::parse(token.pattern.token, &text);
+
+        if !pattern.same_search(&token.pattern) {
+            token.term = Term::new(index, case_matching, max_typos, threads, &pattern);
+        } else if extends_by_one(&pattern.text, &token.pattern.text) {
+            let character = pattern.text.chars().last().expect("pattern text is not empty");
+            token.term.advance(character);
+        } else if extends_by_one(&token.pattern.text, &pattern.text) {
+            token.term.back();
+        } else if pattern.text != token.pattern.text {
+            token.term = Term::new(index, case_matching, max_typos, threads, &pattern);
         }
+
+        token.text = text;
+        token.pattern = pattern;
     }
 
-    pub fn query_string(&self) -> String {
-        self.steps.iter().skip(1).map(|step| step.character).collect()
+    fn clause_matches<C: Fn() -> bool + Sync>(&self, clause: &Clause, cancelled: &C) -> Option<HashMap<String, Match>> {
+        let mut matches: HashMap<String, Match> = HashMap::new();
+
+        for pattern in clause {
+            let term = &self.tokens[pattern.token].term;
+
+            let pattern_matches: Vec<Match> = if pattern.negated {
+                let excluded: HashSet<String> = term.matches_unless(cancelled)?.into_iter().map(|m| m.path).collect();
+
+                self.all_matches(cancelled)?
+                    .into_iter()
+                    .filter(|&(ref path, _)| !excluded.contains(path))
+                    .map(|(path, _)| Match { path: path, score: 0, positions: Vec::new() })
+                    .collect()
+            } else {
+                term.matches_unless(cancelled)?.into_iter().collect()
+            };
+
+            // a path only has to match one pattern in a clause, the best one
+            for m in pattern_matches {
+                match matches.get(&m.path) {
+                    Some(existing) if existing <= &m => continue,
+                    _ => ()
+                }
+
+                matches.insert(m.path.clone(), m);
+            }
+        }
+
+        Some(matches)
     }
 
-    fn current_step(&self) -> &Step {
-        self.steps.last().expect("it should be impossible to have no steps")
+    fn all_matches<C: Fn() -> bool + Sync>(&self, cancelled: &C) -> Option<HashMap<String, Match>> {
+        let pattern = Pattern::parse(0, "");
+
+        Term::new(self.index, self.case_matching, 0, self.threads, &pattern)
+            .matches_unless(cancelled)
+            .map(|matches| matches.into_iter().map(|m| (m.path.clone(), m)).collect())
     }
 }
 
-impl<'a> fmt::Display for Query<'a> {
+fn extends_by_one(longer: &str, shorter: &str) -> bool {
+    longer.starts_with(shorter) && longer[shorter.len()..].chars().count() == 1
+}
+
+impl<'a, T> fmt::Display for Query<'a, T> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let query_string: String = self.steps.iter().map(|step| step.character).collect();
-        write!(f, "{}", query_string)
+        write!(f, "^{}", self.text)
     }
-}
\ No newline at end of file
+}
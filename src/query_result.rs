@@ -2,22 +2,47 @@ use termion::style;
 
 use std::collections::{BinaryHeap, HashSet};
 use std::cmp::Ordering;
-use std::iter::FromIterator;
 use std::fmt;
 
-use query::Match;
-
-pub struct QueryResult {
+pub struct QueryResult<T = ()> {
     pub path: String,
     pub score: usize,
-    pub positions: HashSet<usize>
+    pub positions: HashSet<usize>,
+    pub display: Vec<(usize, usize)>,
+    pub payload: T
+}
+
+impl<T> QueryResult<T> {
+    // the characters that should be shown, along with their position in
+    // the path, spans are separated by a space which isn't part of the path
+    pub fn visible_chars(&self) -> Vec<(Option<usize>, char)> {
+        if self.display.is_empty() {
+            return self.path.char_indices().map(|(i, c)| (Some(i), c)).collect();
+        }
+
+        let mut chars = Vec::new();
+
+        for (n, &(start, end)) in self.display.iter().enumerate() {
+            if n > 0 {
+                chars.push((None, ' '));
+            }
+
+            chars.extend(self.path[start..end].char_indices().map(|(i, c)| (Some(start + i), c)));
+        }
+
+        chars
+    }
+
+    pub fn is_highlighted(&self, position: Option<usize>) -> bool {
+        position.map(|position| self.positions.contains(&position)).unwrap_or(false)
+    }
 }
 
-impl fmt::Display for QueryResult {
+impl<T> fmt::Display for QueryResult<T> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        self.path.char_indices()
+        self.visible_chars().into_iter()
             .map(|(i, c)| {
-                match self.positions.contains(&i) {
+                match self.is_highlighted(i) {
                     true => write!(f, "{}{}{}", style::Bold, c, style::Reset),
                     false => write!(f, "{}", c)
                 }
@@ -28,54 +53,52 @@ impl fmt::Display for QueryResult {
     }
 }
 
-impl From<Match> for QueryResult {
-    fn from(m: Match) -> Self {
-        QueryResult {
-            path: m.path,
-            score: m.score,
-            positions: HashSet::from_iter(m.positions.into_iter())
-        }
-    }
-}
-
-impl PartialEq for QueryResult {
-    fn eq(&self, other: &QueryResult) -> bool {
+impl<T> PartialEq for QueryResult<T> {
+    fn eq(&self, other: &QueryResult<T>) -> bool {
         self.path == other.path
     }
 }
 
-impl Eq for QueryResult {
+impl<T> Eq for QueryResult<T> {
 
 }
 
-impl Ord for QueryResult {
-    fn cmp(&self, other: &QueryResult) -> Ordering {
+impl<T> Ord for QueryResult<T> {
+    fn cmp(&self, other: &QueryResult<T>) -> Ordering {
         other.score.cmp(&self.score)
     }
 }
 
-impl PartialOrd for QueryResult {
-    fn partial_cmp(&self, other: &QueryResult) -> Option<Ordering> {
+impl<T> PartialOrd for QueryResult<T> {
+    fn partial_cmp(&self, other: &QueryResult<T>) -> Option<Ordering> {
         Some(self.cmp(other))
     }
 }
 
-pub struct QueryResults {
-    heap: BinaryHeap<QueryResult>
+pub struct QueryResults<T = ()> {
+    heap: BinaryHeap<QueryResult<T>>
 }
 
-impl QueryResults {
+impl<T> QueryResults<T> {
     pub fn new() -> Self {
         QueryResults { heap: BinaryHeap::new() }
     }
 
-    pub fn insert(&mut self, query_result: QueryResult) {
+    pub fn insert(&mut self, query_result: QueryResult<T>) {
         self.heap.push(query_result)
     }
+
+    pub fn extend(&mut self, query_results: Vec<QueryResult<T>>) {
+        self.heap.extend(query_results)
+    }
+
+    pub fn len(&self) -> usize {
+        self.heap.len()
+    }
 }
 
-impl Iterator for QueryResults {
-    type Item = QueryResult;
+impl<T> Iterator for QueryResults<T> {
+    type Item = QueryResult<T>;
 
     fn next(&mut self) -> Option<Self::Item> {
         self.heap.pop()
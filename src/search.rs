@@ -0,0 +1,210 @@
+use std::collections::BinaryHeap;
+use std::sync::Arc;
+
+use index::Index;
+use latest::{Jobs, Latest};
+use query::{CaseMatching, Match, Mode, Query};
+use query_result::{QueryResult, QueryResults};
+
+// how many matches are looked at between checks for a newer request
+static CANCEL_EVERY: usize = 1024;
+
+// the best results of a query, along with what it was asked for, so that a
+// screen can show them without having the query itself
+pub struct Outcome<T = ()> {
+    pub generation: usize,
+    pub query_string: String,
+    pub mode: Mode,
+    pub results: Vec<QueryResult<T>>,
+    pub matches: usize,
+    pub total: usize
+}
+
+impl<T: Clone> Outcome<T> {
+    pub fn of(query: &Query<T>, limit: usize, generation: usize) -> Outcome<T> {
+        Outcome::collect(query, limit, generation, &|| false).expect("a search that can't be cancelled finishes")
+    }
+
+    // only the best matches are kept while going through them, so that no
+    // more results are made than there is room for. Nothing is returned if
+    // the search is cancelled part of the way through
+    fn collect<C: Fn() -> bool + Sync>(query: &Query<T>, limit: usize, generation: usize, cancelled: &C) -> Option<Outcome<T>> {
+        let mut best: BinaryHeap<Match> = BinaryHeap::new();
+        let mut matches = 0;
+
+        for (i, m) in query.matches_unless(cancelled)?.into_iter().enumerate() {
+            if i > 0 && i % CANCEL_EVERY == 0 && cancelled() {
+                return None;
+            }
+
+            matches += query.index().lines_with(&m.path);
+
+            // the heap has the worst of the best on top
+            best.push(m);
+
+            if best.len() > limit {
+                best.pop();
+            }
+        }
+
+        let mut results = QueryResults::new();
+
+        for m in best.iter() {
+            results.extend(query.index().results(m));
+        }
+
+        Some(Outcome {
+            generation: generation,
+            query_string: query.query_string(),
+            mode: query.mode(),
+            results: results.take(limit).collect(),
+            matches: matches,
+            total: query.index().len()
+        })
+    }
+}
+
+struct Request {
+    query_string: String,
+    mode: Mode,
+    limit: usize
+}
+
+// a query evaluated on its own thread, so that searching a big index never
+// holds up the keys being typed. A search that is overtaken by a newer
+// request stops as soon as it notices and only the outcome of the latest
+// request is sent on
+pub struct Search {
+    requests: Latest<Request>
+}
+
+impl Search {
+    pub fn start<T, F>(index: Arc<Index<T>>, case_matching: CaseMatching, max_typos: usize, respond: F) -> Search
+        where T: Clone + Send + Sync + 'static,
+              F: Fn(Outcome<T>) + Send + 'static
+    {
+        let requests = Latest::start(move |jobs: Jobs<Request>| {
+            let mut query = index.query_with_case_matching(case_matching);
+            query.set_max_typos(max_typos);
+
+            for job in jobs {
+                let cancelled = || job.cancelled();
+                let request = &job.request;
+
+                if !query.set_mode_unless(request.mode, &cancelled) || !query.set_query_string_unless(&request.query_string, &cancelled) {
+                    continue;
+                }
+
+                match Outcome::collect(&query, request.limit, job.generation, &cancelled) {
+                    Some(outcome) => if !cancelled() {
+                        respond(outcome);
+                    },
+                    None => ()
+                }
+            }
+        });
+
+        Search {
+            requests: requests
+        }
+    }
+
+    // returns the generation that the outcome for this request will have
+    pub fn search(&self, query_string: &str, mode: Mode, limit: usize) -> usize {
+        self.requests.push(Request {
+            query_string: query_string.to_owned(),
+            mode: mode,
+            limit: limit
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    fn index() -> Arc<Index> {
+        let mut index = Index::new();
+
+        for path in &["src/main.rs", "src/query.rs", "src/search.rs", "Cargo.toml"] {
+            index.push(path);
+        }
+
+        Arc::new(index)
+    }
+
+    #[test]
+    fn outcomes_arrive() {
+        let (sender, receiver) = mpsc::channel();
+        let search = Search::start(index(), CaseMatching::Smart, 0, move |outcome| {
+            sender.send(outcome).ok();
+        });
+
+        let generation = search.search("srs", Mode::Fuzzy, 10);
+        let outcome = receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+
+        assert_eq!(generation, outcome.generation);
+        assert_eq!("srs", outcome.query_string);
+        assert_eq!(3, outcome.matches);
+        assert_eq!(4, outcome.total);
+    }
+
+    #[test]
+    fn best_results_are_kept() {
+        let index = index();
+        let mut query = index.query();
+        query.set_query_string("s");
+
+        let scores = |results: Vec<QueryResult>| results.into_iter().map(|result| result.score).collect::<Vec<_>>();
+        let outcome = Outcome::of(&query, 2, 0);
+
+        assert_eq!(scores(query.results().take(2).collect()), scores(outcome.results));
+        assert_eq!(3, outcome.matches);
+    }
+
+    #[test]
+    fn cancelled_searches_stop() {
+        let index = index();
+        let mut query = index.query();
+        query.set_query_string("s");
+
+        assert!(Outcome::collect(&query, 10, 1, &|| true).is_none());
+        assert_eq!(None, query.matches_unless(&|| true).map(|matches| matches.len()));
+
+        query.set_mode(Mode::Glob);
+        assert!(!query.set_mode_unless(Mode::Fuzzy, || true));
+        assert_eq!("", query.query_string());
+
+        query.set_query_string("s");
+        assert_eq!(3, Outcome::of(&query, 10, 1).matches);
+    }
+
+    #[test]
+    fn later_requests_win() {
+        let (sender, receiver) = mpsc::channel();
+        let search = Search::start(index(), CaseMatching::Smart, 0, move |outcome| {
+            sender.send(outcome).ok();
+        });
+
+        for query_string in &["s", "se", "sea", "sear"] {
+            search.search(query_string, Mode::Fuzzy, 10);
+        }
+
+        let last = search.search("cargo", Mode::Fuzzy, 1);
+
+        // superseded searches may or may not have got there first, but
+        // nothing comes after the last one
+        let mut outcome = receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+
+        while outcome.generation != last {
+            assert!(outcome.generation < last);
+            outcome = receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+        }
+
+        assert_eq!(vec!["Cargo.toml"], outcome.results.iter().map(|result| result.path.as_str()).collect::<Vec<_>>());
+        assert!(receiver.recv_timeout(Duration::from_millis(100)).is_err());
+    }
+}
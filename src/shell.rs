@@ -0,0 +1,43 @@
+// wraps a string in single quotes so that the shell treats it as a single
+// literal word, whatever characters it contains
+pub fn quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "'\\''"))
+}
+
+// every `{}` in the template is replaced with the quoted path
+pub fn substitute(template: &str, path: &str) -> String {
+    template.replace("{}", &quote(path))
+}
+
+// every `{}` in the template is replaced with all of the paths, each one
+// quoted on its own and separated by spaces
+pub fn substitute_all(template: &str, paths: &[String]) -> String {
+    let quoted: Vec<String> = paths.iter().map(|path| quote(path)).collect();
+    template.replace("{}", &quoted.join(" "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quoting() {
+        assert_eq!("'src/main.rs'", quote("src/main.rs"));
+        assert_eq!("'it'\\''s here'", quote("it's here"));
+        assert_eq!("'$(rm -rf ~)'", quote("$(rm -rf ~)"));
+    }
+
+    #[test]
+    fn substitution() {
+        assert_eq!("head -n 10 'a b.txt'", substitute("head -n 10 {}", "a b.txt"));
+        assert_eq!("diff 'a' 'a'", substitute("diff {} {}", "a"));
+    }
+
+    #[test]
+    fn substitution_of_several_paths() {
+        let paths = vec![String::from("a b.txt"), String::from("it's")];
+
+        assert_eq!("rm 'a b.txt' 'it'\\''s'", substitute_all("rm {}", &paths));
+        assert_eq!("ls", substitute_all("ls", &paths));
+    }
+}
@@ -0,0 +1,180 @@
+// a single space separated token of the query, with any operators removed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pattern {
+    pub token: usize,
+    pub text: String,
+    pub negated: bool,
+    pub exact: bool,
+    pub anchored_start: bool,
+    pub anchored_end: bool
+}
+
+impl Pattern {
+    pub fn parse(token: usize, s: &str) -> Pattern {
+        let mut text = s;
+        let mut negated = false;
+        let mut exact = false;
+        let mut anchored_start = false;
+        let mut anchored_end = false;
+
+        if text.len() > 1 && text.starts_with('!') {
+            negated = true;
+            text = &text[1..];
+        }
+
+        if text.len() > 1 && text.starts_with('\'') {
+            exact = true;
+            text = &text[1..];
+        } else if text.len() > 1 && text.starts_with('^') {
+            anchored_start = true;
+            text = &text[1..];
+        }
+
+        if text.len() > 1 && text.ends_with('$') {
+            anchored_end = true;
+            text = &text[..text.len() - 1];
+        }
+
+        Pattern {
+            token: token,
+            text: text.to_owned(),
+            negated: negated,
+            exact: exact,
+            anchored_start: anchored_start,
+            anchored_end: anchored_end
+        }
+    }
+
+    // everything but plain fuzzy terms have to match a contiguous run of
+    // characters, negated terms would exclude far too much otherwise
+    pub fn is_contiguous(&self) -> bool {
+        self.exact || self.negated || self.anchored_start || self.anchored_end
+    }
+
+    // whether the graph search for the two patterns is the same, negation
+    // only changes how the matches are used
+    pub fn same_search(&self, other: &Pattern) -> bool {
+        self.is_contiguous() == other.is_contiguous() &&
+            self.anchored_start == other.anchored_start &&
+            self.anchored_end == other.anchored_end
+    }
+}
+
+// a query is a conjunction of clauses, each of which is a disjunction of
+// patterns separated by `|`
+pub type Clause = Vec<Pattern>;
+
+pub fn parse(query: &str) -> Vec<Clause> {
+    let mut clauses: Vec<Clause> = Vec::new();
+    let mut or_next = false;
+
+    for (token, s) in query.split(' ').enumerate() {
+        if s.is_empty() {
+            continue;
+        }
+
+        if s == "|" {
+            or_next = !clauses.is_empty();
+            continue;
+        }
+
+        let pattern = Pattern::parse(token, s);
+
+        match clauses.last_mut() {
+            Some(clause) if or_next => clause.push(pattern),
+            _ => clauses.push(vec![pattern])
+        }
+
+        or_next = false;
+    }
+
+    clauses
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pattern(token: usize, text: &str) -> Pattern {
+        Pattern {
+            token: token,
+            text: text.to_owned(),
+            negated: false,
+            exact: false,
+            anchored_start: false,
+            anchored_end: false
+        }
+    }
+
+    #[test]
+    fn fuzzy() {
+        assert_eq!(vec![vec![pattern(0, "abc")]], parse("abc"));
+    }
+
+    #[test]
+    fn multiple_terms() {
+        assert_eq!(vec![vec![pattern(0, "abc")], vec![pattern(2, "def")]], parse("abc  def"));
+    }
+
+    #[test]
+    fn negation() {
+        let mut expected = pattern(0, "abc");
+        expected.negated = true;
+
+        assert_eq!(vec![vec![expected]], parse("!abc"));
+    }
+
+    #[test]
+    fn exact() {
+        let mut expected = pattern(0, "abc");
+        expected.exact = true;
+
+        assert_eq!(vec![vec![expected]], parse("'abc"));
+    }
+
+    #[test]
+    fn anchored_start() {
+        let mut expected = pattern(0, "abc");
+        expected.anchored_start = true;
+
+        assert_eq!(vec![vec![expected]], parse("^abc"));
+    }
+
+    #[test]
+    fn anchored_end() {
+        let mut expected = pattern(0, ".rs");
+        expected.anchored_end = true;
+
+        assert_eq!(vec![vec![expected]], parse(".rs$"));
+    }
+
+    #[test]
+    fn combined_operators() {
+        let mut expected = pattern(0, "abc");
+        expected.negated = true;
+        expected.anchored_start = true;
+        expected.anchored_end = true;
+
+        assert_eq!(vec![vec![expected]], parse("!^abc$"));
+    }
+
+    #[test]
+    fn lone_operators_are_literal() {
+        assert_eq!(vec![vec![pattern(0, "!")], vec![pattern(1, "^")], vec![pattern(2, "$")]], parse("! ^ $"));
+    }
+
+    #[test]
+    fn or() {
+        let expected = vec![
+            vec![pattern(0, "abc")],
+            vec![pattern(1, "def"), pattern(3, "ghi")]
+        ];
+
+        assert_eq!(expected, parse("abc def | ghi"));
+    }
+
+    #[test]
+    fn dangling_or() {
+        assert_eq!(vec![vec![pattern(1, "abc")]], parse("| abc |"));
+    }
+}
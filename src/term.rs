@@ -0,0 +1,501 @@
+use std::collections::HashSet;
+use std::cmp::{Eq, PartialEq, PartialOrd, Ord, Ordering};
+use std::hash::{Hash, Hasher};
+use std::mem;
+
+use crossbeam;
+use libc;
+
+use index::{Index, Graph, Node, Edge};
+use normalize::{self, Normalization};
+use query::Match;
+use min_set::MinSet;
+use syntax::Pattern;
+
+// the cost of each typo, enough that a path with a typo will generally rank
+// below one that matches exactly
+static TYPO_PENALTY: usize = 10;
+
+// below this many cursors a step is quicker to take on a single thread than
+// it is to hand out to several
+static PARALLEL_CURSORS: usize = 2048;
+
+// how many cursors are turned into matches between checks for whether
+// they are still wanted
+static CANCEL_CURSORS: usize = 1024;
+
+#[derive(Debug, Clone)]
+struct Cursor {
+    node: Node,
+    score: usize,
+    positions: Vec<usize>,
+    typos: usize
+}
+
+impl PartialOrd for Cursor {
+    fn partial_cmp(&self, other: &Cursor) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+// ties are broken on the positions, so that which of two equally good
+// cursors survives doesn't depend on the order they were found in
+impl Ord for Cursor {
+    fn cmp(&self, other: &Cursor) -> Ordering {
+        self.score.cmp(&other.score).then_with(|| self.positions.cmp(&other.positions))
+    }
+}
+
+// cursors with a different number of typos are kept apart, otherwise a
+// cheaper path with more typos could use up the budget for a better one
+impl Hash for Cursor {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.node.hash(state);
+        self.typos.hash(state);
+    }
+}
+
+impl PartialEq for Cursor {
+    fn eq(&self, other: &Cursor) -> bool {
+        self.node == other.node && self.typos == other.typos
+    }
+}
+
+impl Eq for Cursor {
+
+}
+
+impl Cursor {
+    fn extend(&self, edge: &Edge) -> Cursor {
+        let mut positions = self.positions.clone();
+
+        // the end of the path adds to the score, but isn't a character
+        if !edge.end {
+            positions.push(edge.position);
+        }
+
+        Cursor {
+            node: edge.path.to_owned(),
+            score: self.score + edge.score,
+            positions: positions,
+            typos: self.typos
+        }
+    }
+
+    fn typo(&self) -> Cursor {
+        let mut cursor = self.clone();
+        cursor.score = cursor.score + TYPO_PENALTY;
+        cursor.typos = cursor.typos + 1;
+        cursor
+    }
+
+    fn extend_folded(&self, edge: &Edge, character: char) -> Cursor {
+        let mut cursor = self.extend(edge);
+
+        // matching an uppercase path character with a lowercase query
+        // character is slightly preferred, it is usually a word begin
+        if cursor.score > 0 && character.is_uppercase() {
+            cursor.score = cursor.score - 1;
+        }
+
+        cursor
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CaseMatching {
+    Insensitive,
+    Sensitive,
+    Smart
+}
+
+impl Default for CaseMatching {
+    fn default() -> CaseMatching {
+        CaseMatching::Smart
+    }
+}
+
+impl CaseMatching {
+    pub fn is_sensitive(&self, query_string: &str) -> bool {
+        match *self {
+            CaseMatching::Insensitive => false,
+            CaseMatching::Sensitive => true,
+            CaseMatching::Smart => query_string.chars().any(|c| c.is_uppercase())
+        }
+    }
+}
+
+// the graph only stores characters exactly as they appear in the path, so
+// any case folding has to happen when following edges
+fn folded_characters(character: char) -> Vec<char> {
+    let mut folded: Vec<char> = Vec::new();
+
+    for mapping in vec![character.to_uppercase().collect::<Vec<char>>(), character.to_lowercase().collect()] {
+        // multi character mappings have already been expanded by normalisation
+        if mapping.len() != 1 {
+            continue;
+        }
+
+        let c = mapping[0];
+
+        if c != character && !folded.contains(&c) {
+            folded.push(c);
+        }
+    }
+
+    folded
+}
+
+#[derive(Debug)]
+struct Step {
+    character: char,
+    case_sensitive: bool,
+    cursors: HashSet<Cursor>
+}
+
+impl Step {
+    fn first() -> Step {
+        let cursor = Cursor {
+            node: String::from(""),
+            score: 0,
+            positions: Vec::new(),
+            typos: 0
+        };
+
+        let mut cursors = HashSet::new();
+        cursors.insert(cursor);
+
+        Step {
+            character: '^',
+            case_sensitive: false,
+            cursors: cursors
+        }
+    }
+
+    fn new(character: char, case_sensitive: bool) -> Step {
+        Step {
+            character: character,
+            case_sensitive: case_sensitive,
+            cursors: HashSet::new()
+        }
+    }
+
+    // the cursors found by each thread are combined in the same way as
+    // the cursors found by one
+    fn merge(&mut self, other: Step) {
+        if self.cursors.is_empty() {
+            self.cursors = other.cursors;
+            return;
+        }
+
+        for cursor in other.cursors {
+            self.push(cursor);
+        }
+    }
+
+    fn push(&mut self, cursor: Cursor) {
+        match self.cursors.take(&cursor) {
+            Some(ref existing) if existing > &cursor => {
+                self.cursors.insert(cursor);
+            },
+
+            Some(existing) => {
+                self.cursors.insert(existing);
+            }
+
+            None => {
+                self.cursors.insert(cursor);
+            }
+        }
+    }
+}
+
+impl From<Cursor> for Match {
+    fn from(cursor: Cursor) -> Match {
+        Match {
+            path: cursor.node,
+            score: cursor.score,
+            positions: cursor.positions
+        }
+    }
+}
+
+// a single term of a query, this is a search through the graph for the
+// characters of one pattern, one step per character
+pub struct Term<'a> {
+    graph: &'a Graph,
+    normalization: Normalization,
+    case_matching: CaseMatching,
+    max_typos: usize,
+    threads: usize,
+    contiguous: bool,
+    anchored_start: bool,
+    anchored_end: bool,
+    steps: Vec<Step>
+}
+
+// no more than asked for, or one for each processor when left at zero
+fn available_threads(threads: usize) -> usize {
+    if threads > 0 {
+        return threads;
+    }
+
+    match unsafe { libc::sysconf(libc::_SC_NPROCESSORS_ONLN) } {
+        n if n > 0 => n as usize,
+        _ => 1
+    }
+}
+
+impl<'a> Term<'a> {
+    pub fn new<T>(index: &'a Index<T>, case_matching: CaseMatching, max_typos: usize, threads: usize, pattern: &Pattern) -> Term<'a> {
+        let step = Step::first();
+
+        let mut term = Term {
+            graph: index.graph(),
+            normalization: index.normalization(),
+            case_matching: case_matching,
+            max_typos: max_typos,
+            threads: threads,
+            contiguous: pattern.is_contiguous(),
+            anchored_start: pattern.anchored_start,
+            anchored_end: pattern.anchored_end,
+            steps: vec![step]
+        };
+
+        for character in pattern.text.chars() {
+            term.advance(character);
+        }
+
+        term
+    }
+
+    // every path the term matches, unless it is cancelled part of the way
+    // through
+    pub fn matches_unless<C: Fn() -> bool + Sync>(&self, cancelled: &C) -> Option<MinSet<Match>> {
+        let sets = self.in_parallel(&self.current_step().cursors, |cursors| {
+            let mut match_set: MinSet<Match> = MinSet::new();
+
+            for (i, cursor) in cursors.iter().filter(|cursor| cursor.typos == 0 || cursor.positions.len() > cursor.typos).enumerate() {
+                if i % CANCEL_CURSORS == 0 && cancelled() {
+                    return None;
+                }
+
+                let edge_map = &self.graph[&cursor.node];
+
+                edge_map.get(&'$').and_then(|edges| {
+                    for edge in edges.iter().filter(|edge| edge.end) {
+                        if self.anchored_end && !edge.contiguous {
+                            continue;
+                        }
+
+                        match_set.insert(cursor.extend(edge).into())
+                    };
+
+                    Some(())
+                });
+            }
+
+            Some(match_set)
+        });
+
+        let mut sets = sets.into_iter();
+        let mut match_set = sets.next().unwrap_or_else(|| Some(MinSet::new()))?;
+
+        for set in sets {
+            match_set.extend(set?);
+        }
+
+        Some(match_set)
+    }
+
+    pub fn advance(&mut self, character: char) {
+        let mut query_string = self.query_string();
+        query_string.push(character);
+
+        let case_sensitive = self.case_matching.is_sensitive(&query_string);
+
+        // with smart case a single uppercase character changes how every
+        // previous character should have matched, so start again
+        if self.steps.len() > 1 && case_sensitive != self.current_step().case_sensitive {
+            self.replay(&query_string);
+            return;
+        }
+
+        let next_step = self.next_step(character, case_sensitive);
+        self.steps.push(next_step);
+    }
+
+    pub fn back(&mut self) {
+        if self.steps.len() > 1 {
+            self.steps.pop();
+        }
+
+        let query_string = self.query_string();
+        let case_sensitive = self.case_matching.is_sensitive(&query_string);
+
+        if self.steps.len() > 1 && case_sensitive != self.current_step().case_sensitive {
+            self.replay(&query_string);
+        }
+    }
+
+    pub fn query_string(&self) -> String {
+        self.steps.iter().skip(1).map(|step| step.character).collect()
+    }
+
+    fn current_step(&self) -> &Step {
+        self.steps.last().expect("it should be impossible to have no steps")
+    }
+
+    fn next_step(&self, character: char, case_sensitive: bool) -> Step {
+        let mut query_string = self.query_string();
+        query_string.push(character);
+
+        // a character that continues the ones before it, like a combining
+        // accent, is matched along with them as one normalised cluster, from
+        // the step before the cluster began
+        let start = normalize::last_cluster(&query_string);
+        let before = query_string[..start].chars().count();
+        let characters = self.normalization.characters(&query_string[start..]);
+
+        let mut next_step = self.follow(&self.steps[before].cursors, character, &characters, case_sensitive);
+
+        // typos only make sense when looking for a fuzzy match
+        if self.max_typos > 0 && !self.contiguous {
+            self.push_typos(&mut next_step, case_sensitive);
+        }
+
+        next_step
+    }
+
+    fn follow(&self, cursors: &HashSet<Cursor>, character: char, characters: &[char], case_sensitive: bool) -> Step {
+        let mut next_step = Step::new(character, case_sensitive);
+        next_step.cursors = cursors.clone();
+
+        // a typed character can normalise to several (ß => ss), or none at
+        // all, each of these has to be followed before the step is complete
+        for &c in characters {
+            let cursors = mem::replace(&mut next_step.cursors, HashSet::new());
+            let folded = if case_sensitive { Vec::new() } else { folded_characters(c) };
+
+            let steps = self.in_parallel(&cursors, |cursors| {
+                let mut step = Step::new(character, case_sensitive);
+
+                for cursor in cursors {
+                    self.follow_cursor(cursor, c, &folded, &mut step);
+                }
+
+                step
+            });
+
+            for step in steps {
+                next_step.merge(step);
+            }
+        }
+
+        next_step
+    }
+
+    fn follow_cursor(&self, cursor: &Cursor, c: char, folded: &[char], next_step: &mut Step) {
+        let edge_map = &self.graph[&cursor.node];
+
+        edge_map.get(&c).and_then(|edges| {
+            for edge in edges.iter().filter(|edge| !edge.end && self.can_follow(cursor, edge)) {
+                next_step.push(cursor.extend(&edge));
+            };
+
+            Some(()) // we just have to return _something_
+        });
+
+        for &folded_character in folded {
+            edge_map.get(&folded_character).and_then(|edges| {
+                for edge in edges.iter().filter(|edge| !edge.end && self.can_follow(cursor, edge)) {
+                    next_step.push(cursor.extend_folded(&edge, folded_character));
+                };
+
+                Some(())
+            });
+        }
+    }
+
+    // big steps are split into a chunk of cursors for each thread, the
+    // results come back in the order of the chunks
+    fn in_parallel<'c, R, F>(&self, cursors: &'c HashSet<Cursor>, f: F) -> Vec<R>
+        where R: Send, F: Fn(&[&'c Cursor]) -> R + Sync
+    {
+        let cursors: Vec<&Cursor> = cursors.iter().collect();
+        let threads = match cursors.len() < PARALLEL_CURSORS {
+            true => 1,
+            false => available_threads(self.threads)
+        };
+
+        if threads < 2 {
+            return vec![f(&cursors)];
+        }
+
+        let size = (cursors.len() + threads - 1) / threads;
+        let f = &f;
+
+        crossbeam::scope(|scope| {
+            let handles: Vec<_> = cursors.chunks(size)
+                .map(|chunk| scope.spawn(move || f(chunk)))
+                .collect();
+
+            handles.into_iter().map(|handle| handle.join()).collect()
+        })
+    }
+
+    // a typed character that doesn't belong can be skipped, since gaps are
+    // free anyway this covers substituted characters too. The last two typed
+    // characters can also be swapped. Both cost a typo, any character can be
+    // skipped but a match needs more real characters than typos, so that
+    // every path doesn't match
+    fn push_typos(&self, next_step: &mut Step, case_sensitive: bool) {
+        let current_step = self.current_step();
+
+        for cursor in &current_step.cursors {
+            if cursor.typos < self.max_typos {
+                next_step.push(cursor.typo());
+            }
+        }
+
+        if self.steps.len() < 2 {
+            return;
+        }
+
+        let previous_cursors: HashSet<Cursor> = self.steps[self.steps.len() - 2].cursors.iter()
+            .filter(|cursor| cursor.typos < self.max_typos)
+            .cloned()
+            .collect();
+
+        let next_characters = self.normalization.characters(&next_step.character.to_string());
+        let current_characters = self.normalization.characters(&current_step.character.to_string());
+
+        let swapped = self.follow(&previous_cursors, next_step.character, &next_characters, case_sensitive);
+        let swapped = self.follow(&swapped.cursors, current_step.character, &current_characters, case_sensitive);
+
+        for cursor in swapped.cursors {
+            next_step.push(cursor.typo());
+        }
+    }
+
+    // contiguous terms can begin anywhere, unless anchored to the start, but
+    // every following character has to come straight after the last
+    fn can_follow(&self, cursor: &Cursor, edge: &Edge) -> bool {
+        if !self.contiguous || edge.contiguous {
+            return true;
+        }
+
+        cursor.positions.is_empty() && !self.anchored_start
+    }
+
+    fn replay(&mut self, query_string: &str) {
+        let case_sensitive = self.case_matching.is_sensitive(query_string);
+
+        self.steps.truncate(1);
+
+        for character in query_string.chars() {
+            let next_step = self.next_step(character, case_sensitive);
+            self.steps.push(next_step);
+        }
+    }
+}
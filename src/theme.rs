@@ -0,0 +1,291 @@
+use std::fmt;
+
+use termion::{color, style};
+
+// colours are given as names for the basic sixteen, or as a number from the
+// 256 colour palette
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Color(pub u8);
+
+impl Color {
+    pub fn parse(s: &str) -> Option<Color> {
+        let names = [
+            "black", "red", "green", "yellow", "blue", "magenta", "cyan", "white",
+            "bright-black", "bright-red", "bright-green", "bright-yellow",
+            "bright-blue", "bright-magenta", "bright-cyan", "bright-white"
+        ];
+
+        match names.iter().position(|&name| name == s) {
+            Some(i) => Some(Color(i as u8)),
+            None => s.parse().ok().map(Color)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Style {
+    pub fg: Option<Color>,
+    pub bg: Option<Color>,
+    pub bold: bool,
+    pub underline: bool,
+    pub reverse: bool
+}
+
+impl Style {
+    // `+` separated attributes, a colour on its own is the foreground,
+    // `bg:colour` the background, e.g. `red+bold` or `bg:236+underline`
+    pub fn parse(s: &str) -> Option<Style> {
+        let mut style = Style::default();
+
+        for attribute in s.split('+') {
+            match attribute {
+                "bold" => style.bold = true,
+                "underline" => style.underline = true,
+                "reverse" => style.reverse = true,
+                "plain" => (),
+                _ if attribute.starts_with("bg:") => style.bg = Some(Color::parse(&attribute[3..])?),
+                _ if attribute.starts_with("fg:") => style.fg = Some(Color::parse(&attribute[3..])?),
+                _ => style.fg = Some(Color::parse(attribute)?)
+            }
+        }
+
+        Some(style)
+    }
+
+    pub fn is_plain(&self) -> bool {
+        *self == Style::default()
+    }
+
+    // undoes this style, without writing anything if there was nothing to undo
+    pub fn reset(&self) -> Reset {
+        Reset(self.is_plain())
+    }
+}
+
+impl fmt::Display for Style {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.bold {
+            write!(f, "{}", style::Bold)?;
+        }
+
+        if self.underline {
+            write!(f, "{}", style::Underline)?;
+        }
+
+        if self.reverse {
+            write!(f, "{}", style::Invert)?;
+        }
+
+        if let Some(Color(fg)) = self.fg {
+            write!(f, "{}", color::Fg(color::AnsiValue(fg)))?;
+        }
+
+        if let Some(Color(bg)) = self.bg {
+            write!(f, "{}", color::Bg(color::AnsiValue(bg)))?;
+        }
+
+        Ok(())
+    }
+}
+
+pub struct Reset(bool);
+
+impl fmt::Display for Reset {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0 {
+            true => Ok(()),
+            false => write!(f, "{}", style::Reset)
+        }
+    }
+}
+
+// how each part of the screen is drawn
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Theme {
+    pub prompt: Style,
+    pub selected: Style,
+    pub gutter: Style,
+    pub matched: Style,
+    pub marked: Style
+}
+
+impl Default for Theme {
+    fn default() -> Theme {
+        Theme::dark()
+    }
+}
+
+impl Theme {
+    pub fn dark() -> Theme {
+        Theme {
+            prompt: Style { bold: true, ..Style::default() },
+            selected: Style { bg: Some(Color(0)), ..Style::default() },
+            gutter: Style { bg: Some(Color(0)), ..Style::default() },
+            matched: Style { bold: true, ..Style::default() },
+            marked: Style { fg: Some(Color(3)), ..Style::default() }
+        }
+    }
+
+    pub fn light() -> Theme {
+        Theme {
+            prompt: Style { bold: true, ..Style::default() },
+            selected: Style { bg: Some(Color(252)), ..Style::default() },
+            gutter: Style { bg: Some(Color(252)), ..Style::default() },
+            matched: Style { fg: Some(Color(4)), bold: true, ..Style::default() },
+            marked: Style { fg: Some(Color(5)), ..Style::default() }
+        }
+    }
+
+    // no colours, for terminals where they can't be relied on
+    pub fn mono() -> Theme {
+        Theme {
+            prompt: Style { bold: true, ..Style::default() },
+            selected: Style { reverse: true, ..Style::default() },
+            gutter: Style::default(),
+            matched: Style { bold: true, underline: true, ..Style::default() },
+            marked: Style { bold: true, ..Style::default() }
+        }
+    }
+
+    // nothing but plain text
+    pub fn none() -> Theme {
+        Theme {
+            prompt: Style::default(),
+            selected: Style::default(),
+            gutter: Style::default(),
+            matched: Style::default(),
+            marked: Style::default()
+        }
+    }
+
+    pub fn preset(name: &str) -> Option<Theme> {
+        match name {
+            "dark" => Some(Theme::dark()),
+            "light" => Some(Theme::light()),
+            "mono" => Some(Theme::mono()),
+            "none" => Some(Theme::none()),
+            _ => None
+        }
+    }
+
+    // comma separated presets and `element=style` overrides, applied in
+    // order, e.g. `light,selected=bg:24+bold,match=red`
+    pub fn parse(s: &str) -> Option<Theme> {
+        let mut theme = Theme::default();
+
+        for item in s.split(',').filter(|item| !item.is_empty()) {
+            let equals = match item.find('=') {
+                Some(equals) => equals,
+                None => {
+                    theme = Theme::preset(item)?;
+                    continue;
+                }
+            };
+
+            let style = Style::parse(&item[equals + 1..])?;
+
+            match &item[..equals] {
+                "prompt" => theme.prompt = style,
+                "selected" => theme.selected = style,
+                "gutter" => theme.gutter = style,
+                "match" => theme.matched = style,
+                "marked" => theme.marked = style,
+                _ => return None
+            }
+        }
+
+        Some(theme)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorChoice {
+    Never,
+    Auto,
+    Always
+}
+
+impl Default for ColorChoice {
+    fn default() -> ColorChoice {
+        ColorChoice::Auto
+    }
+}
+
+impl ColorChoice {
+    pub fn parse(s: &str) -> Option<ColorChoice> {
+        match s {
+            "never" => Some(ColorChoice::Never),
+            "auto" => Some(ColorChoice::Auto),
+            "always" => Some(ColorChoice::Always),
+            _ => None
+        }
+    }
+
+    // left to itself colour is used unless NO_COLOR is set, to anything at
+    // all, or the terminal says it can't handle escape codes
+    pub fn use_color(&self, no_color: Option<&str>, term: Option<&str>) -> bool {
+        match *self {
+            ColorChoice::Never => false,
+            ColorChoice::Always => true,
+            ColorChoice::Auto => no_color.map(|value| value.is_empty()).unwrap_or(true) && term != Some("dumb")
+        }
+    }
+
+    // without colour there are still bold, underline and reverse, which
+    // NO_COLOR allows, unless the terminal can't show those either
+    pub fn theme(&self, theme: Theme, no_color: Option<&str>, term: Option<&str>) -> Theme {
+        match (self.use_color(no_color, term), term) {
+            (true, _) => theme,
+            (false, Some("dumb")) => Theme::none(),
+            (false, _) => Theme::mono()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_styles() {
+        assert_eq!(Some(Style { fg: Some(Color(1)), bold: true, ..Style::default() }), Style::parse("red+bold"));
+        assert_eq!(Some(Style { bg: Some(Color(236)), underline: true, ..Style::default() }), Style::parse("bg:236+underline"));
+        assert_eq!(Some(Style::default()), Style::parse("plain"));
+        assert_eq!(None, Style::parse("bg:mauve"));
+    }
+
+    #[test]
+    fn parse_themes() {
+        let theme = Theme::parse("light,selected=bg:24+bold,match=bright-red").unwrap();
+
+        assert_eq!(Theme::light().prompt, theme.prompt);
+        assert_eq!(Style { bg: Some(Color(24)), bold: true, ..Style::default() }, theme.selected);
+        assert_eq!(Some(Color(9)), theme.matched.fg);
+
+        assert_eq!(None, Theme::parse("solarized"));
+        assert_eq!(None, Theme::parse("background=red"));
+    }
+
+    #[test]
+    fn plain_styles_write_nothing() {
+        let style = Style::default();
+
+        assert_eq!("", format!("{}{}", style, style.reset()));
+        assert_eq!(format!("{}{}", style::Bold, style::Reset), format!("{}{}", Theme::dark().prompt, Theme::dark().prompt.reset()));
+    }
+
+    #[test]
+    fn color_choice() {
+        assert!(ColorChoice::Auto.use_color(None, Some("xterm")));
+        assert!(ColorChoice::Auto.use_color(Some(""), None));
+        assert!(!ColorChoice::Auto.use_color(Some("1"), Some("xterm")));
+        assert!(!ColorChoice::Auto.use_color(None, Some("dumb")));
+        assert!(ColorChoice::Always.use_color(Some("1"), Some("dumb")));
+        assert!(!ColorChoice::Never.use_color(None, Some("xterm")));
+
+        assert_eq!(Theme::mono(), ColorChoice::Never.theme(Theme::light(), None, None));
+        assert_eq!(Theme::mono(), ColorChoice::Auto.theme(Theme::light(), Some("1"), Some("xterm")));
+        assert_eq!(Theme::none(), ColorChoice::Auto.theme(Theme::light(), None, Some("dumb")));
+        assert_eq!(Theme::light(), ColorChoice::Auto.theme(Theme::light(), None, Some("xterm")));
+    }
+}
@@ -1,41 +1,241 @@
-use std::fmt;
+use std::fmt::Write;
+use std::io;
 use std::collections::VecDeque;
 
-use termion::{cursor, style, clear, color};
+use termion::{self, cursor, clear};
 
-use query::Query;
+use preview::{Position, Preview};
+use query::Mode;
 use query_result::QueryResult;
+use search::Outcome;
+use theme::{Style, Theme};
+use width;
 
 static PROMPT: &'static str = ">>> ";
 
-pub struct Screen {
+static SPINNER: &'static [char] = &['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+
+pub struct Screen<T = ()> {
     query_string: String,
-    pre_selected: VecDeque<QueryResult>,
-    pub selected: Option<QueryResult>,
-    post_selected: VecDeque<QueryResult>
+    prompt_cursor: usize,
+    pre_selected: VecDeque<QueryResult<T>>,
+    pub selected: Option<QueryResult<T>>,
+    post_selected: VecDeque<QueryResult<T>>,
+    size: (u16, u16),
+    preview_position: Option<Position>,
+    preview: Option<Preview>,
+    theme: Theme,
+    drawn: Option<Vec<String>>,
+    status: Status,
+    marked: Vec<String>,
+    shown: Option<(String, Mode)>
+}
+
+// what goes on the line below the prompt
+#[derive(Debug, Default)]
+struct Status {
+    matches: usize,
+    total: usize,
+    mode: Mode,
+    loading: bool,
+    searching: bool,
+    spinner: usize,
+    error: Option<String>
 }
 
-impl Screen {
-    pub fn new() -> Screen {
+impl<T: Clone> Screen<T> {
+    pub fn new() -> Screen<T> {
         Screen {
             query_string: String::from(""),
+            prompt_cursor: 0,
             pre_selected: VecDeque::new(),
             selected: None,
-            post_selected: VecDeque::new()
+            post_selected: VecDeque::new(),
+            size: termion::terminal_size().unwrap_or((80, 24)),
+            preview_position: None,
+            preview: None,
+            theme: Theme::default(),
+            drawn: None,
+            status: Status::default(),
+            marked: Vec::new(),
+            shown: None
+        }
+    }
+
+    pub fn set_theme(&mut self, theme: Theme) {
+        self.theme = theme;
+    }
+
+    // the number of results shown depends on the size, so they have to be
+    // fetched again from the query afterwards
+    pub fn set_size(&mut self, size: (u16, u16)) {
+        self.size = size;
+        self.invalidate();
+    }
+
+    pub fn show_preview(&mut self, position: Position) {
+        self.preview_position = Some(position);
+    }
+
+    // the path that still needs a preview loading, if any
+    pub fn preview_path(&self) -> Option<&str> {
+        if self.preview_position.is_none() {
+            return None;
+        }
+
+        self.selected.as_ref()
+            .map(|result| result.path.as_ref())
+            .filter(|&path| self.preview.as_ref().map(|preview| preview.path != path).unwrap_or(true))
+    }
+
+    // previews are loaded in the background, by the time one arrives the
+    // selection might have moved on
+    pub fn set_preview(&mut self, preview: Preview) {
+        let current = self.selected.as_ref().map(|result| result.path == preview.path).unwrap_or(false);
+
+        if current {
+            self.preview = Some(preview);
         }
     }
 
-    pub fn current_query(&mut self, query: &Query) {
+    pub fn scroll_preview_down(&mut self) {
+        let lines = self.preview_height() / 2;
+        self.preview.as_mut().map(|preview| preview.scroll_down(lines));
+    }
+
+    pub fn scroll_preview_up(&mut self) {
+        let lines = self.preview_height() / 2;
+        self.preview.as_mut().map(|preview| preview.scroll_up(lines));
+    }
+
+    // the results arrive after the query string that they are for, which
+    // may well have been edited again in the meantime, so the prompt is
+    // left as it is
+    pub fn show(&mut self, outcome: Outcome<T>) {
+        // the same query searched again, after a resize or a reload, keeps
+        // the selection where it was for as far as there are rows
+        let shown = (outcome.query_string, outcome.mode);
+        let selected = match self.shown.as_ref() == Some(&shown) {
+            true => self.pre_selected.len(),
+            false => 0
+        };
+
         self.reset();
+        self.shown = Some(shown);
 
-        self.query_string = query.query_string();
+        self.status.matches = outcome.matches;
+        self.status.total = outcome.total;
+        self.status.mode = outcome.mode;
 
-        for query_result in query.results().take(10) {
+        for query_result in outcome.results.into_iter().take(self.result_rows()) {
             match self.selected {
                 Some(_) => self.post_selected.push_back(query_result),
                 None => self.selected = Some(query_result)
             }
         }
+
+        for _ in 0..selected {
+            self.move_selection_down();
+        }
+    }
+
+    pub fn set_query_string(&mut self, query_string: &str) {
+        self.query_string = query_string.to_owned();
+        self.prompt_cursor = self.prompt_cursor.min(self.query_string.chars().count());
+    }
+
+    // where edits to the query happen, counted in characters
+    pub fn prompt_cursor(&self) -> usize {
+        self.prompt_cursor
+    }
+
+    pub fn set_prompt_cursor(&mut self, cursor: usize) {
+        self.prompt_cursor = cursor.min(self.query_string.chars().count());
+    }
+
+    // puts the prompt cursor in front of the character drawn at the given
+    // column, or at the end if the column is past the query
+    pub fn click_prompt(&mut self, column: u16) {
+        let start = self.prompt_start();
+        let mut used = width::width(PROMPT) + 1;
+        let mut cursor = self.query_string[..start].chars().count();
+
+        for grapheme in width::graphemes(&self.query_string[start..]) {
+            used += width::grapheme_width(grapheme);
+
+            if used > column as usize {
+                break;
+            }
+
+            cursor += grapheme.chars().count();
+        }
+
+        self.set_prompt_cursor(cursor);
+    }
+
+    // the result drawn on the given row of the screen, counted from the
+    // first result
+    pub fn result_at(&self, row: u16) -> Option<usize> {
+        let count = self.pre_selected.len() + self.selected.iter().count() + self.post_selected.len();
+
+        match (row as usize).checked_sub(3) {
+            Some(i) if i < count => Some(i),
+            _ => None
+        }
+    }
+
+    pub fn select(&mut self, i: usize) {
+        while self.pre_selected.len() > i && !self.pre_selected.is_empty() {
+            self.move_selection_up();
+        }
+
+        while self.pre_selected.len() < i && !self.post_selected.is_empty() {
+            self.move_selection_down();
+        }
+    }
+
+    // while the candidates are still being read, or the results for the
+    // query haven't arrived yet, a spinner is shown
+    pub fn set_loading(&mut self, loading: bool) {
+        self.status.loading = loading;
+    }
+
+    pub fn set_searching(&mut self, searching: bool) {
+        self.status.searching = searching;
+    }
+
+    // why the candidates couldn't be loaded, until they next are
+    pub fn set_error(&mut self, error: Option<String>) {
+        self.status.error = error;
+    }
+
+    pub fn tick(&mut self) {
+        self.status.spinner = (self.status.spinner + 1) % SPINNER.len();
+    }
+
+    // marks, or unmarks, the selected result and moves on to the next one
+    pub fn toggle_mark(&mut self) {
+        let path = match self.selected {
+            Some(ref result) => result.path.clone(),
+            None => return
+        };
+
+        match self.marked.iter().position(|marked| marked == &path) {
+            Some(i) => { self.marked.remove(i); },
+            None => self.marked.push(path)
+        }
+
+        self.move_selection_down();
+    }
+
+    // every marked path, in the order they were marked, or just the
+    // selected one when nothing is marked
+    pub fn accepted(&self) -> Vec<String> {
+        if !self.marked.is_empty() {
+            return self.marked.clone();
+        }
+
+        self.selected.iter().map(|result| result.path.clone()).collect()
     }
 
     pub fn move_selection_down(&mut self) {
@@ -67,53 +267,499 @@ impl Screen {
     }
 }
 
-// TODO: clean up the format param names a bit...
-impl fmt::Display for Screen {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}{}", clear::All, cursor::Goto(1, 1))?;
-        write!(f, "{bold}{prompt}{query}{reset}\n\r",
-            bold = style::Bold, prompt = PROMPT, query = self.query_string, reset = style::Reset)?;
+impl<T> Screen<T> {
+    // whether there is anything being waited for, which the spinner shows
+    pub fn is_busy(&self) -> bool {
+        self.status.loading || self.status.searching
+    }
+
+    // every row below the prompt and status, unless it is shared with a
+    // preview below
+    pub fn result_rows(&self) -> usize {
+        let rows = (self.size.1 as usize).saturating_sub(2);
+
+        match self.preview_position {
+            Some(Position::Bottom) => rows / 2,
+            _ => rows
+        }.max(1)
+    }
+
+    // the results share the width of the screen with a preview on the right
+    fn results_width(&self) -> usize {
+        match self.preview_position {
+            Some(Position::Right) => self.size.0 as usize / 2,
+            _ => self.size.0 as usize
+        }
+    }
+
+    // a preview at the bottom goes below the results and a separator
+    fn preview_top(&self) -> usize {
+        match self.preview_position {
+            Some(Position::Bottom) => self.result_rows() + 4,
+            _ => 2
+        }
+    }
+
+    fn preview_height(&self) -> usize {
+        (self.size.1 as usize + 1).saturating_sub(self.preview_top())
+    }
+
+    // forgets what is on the terminal, so the next frame is drawn in full,
+    // needed whenever something else has drawn over it
+    pub fn invalidate(&mut self) {
+        self.drawn = None;
+    }
+
+    // only the rows that have changed since the last frame are drawn, and
+    // the whole frame goes to the terminal in a single write
+    pub fn draw<W: io::Write>(&mut self, out: &mut W) -> io::Result<()> {
+        let frame = self.frame();
+        let mut buffer = String::new();
+
+        let drawn = match self.drawn.take() {
+            Some(drawn) => drawn,
+            None => {
+                buffer.push_str(&clear::All.to_string());
+                Vec::new()
+            }
+        };
+
+        for (i, row) in frame.iter().enumerate() {
+            if drawn.get(i) != Some(row) {
+                write!(buffer, "{}{}{}", cursor::Goto(1, i as u16 + 1), row, clear::UntilNewline).ok();
+            }
+        }
+
+        for i in frame.len()..drawn.len() {
+            write!(buffer, "{}{}", cursor::Goto(1, i as u16 + 1), clear::CurrentLine).ok();
+        }
+
+        buffer.push_str(&self.cursor().to_string());
+
+        out.write_all(buffer.as_bytes())?;
+        out.flush()?;
+
+        self.drawn = Some(frame);
+
+        Ok(())
+    }
+
+    // the cursor is measured in columns, rather than characters
+    fn cursor(&self) -> cursor::Goto {
+        let before = &self.query_string[self.prompt_start()..self.prompt_cursor_byte()];
+        let column = width::width(PROMPT) + width::width(before) + 1;
+        cursor::Goto(column.min(self.size.0 as usize).max(1) as u16, 1)
+    }
+
+    fn prompt_cursor_byte(&self) -> usize {
+        self.query_string.char_indices().nth(self.prompt_cursor).map(|(i, _)| i).unwrap_or(self.query_string.len())
+    }
+
+    // where the part of the query shown on the prompt row starts. A query
+    // too long for the row loses clusters from its start until the cursor,
+    // and the column after it, fit
+    fn prompt_start(&self) -> usize {
+        let columns = (self.size.0 as usize).saturating_sub(width::width(PROMPT) + 1);
+        let end = self.prompt_cursor_byte();
+        let mut start = 0;
+
+        while width::width(&self.query_string[start..end]) > columns {
+            start += width::graphemes(&self.query_string[start..end])[0].len();
+        }
+
+        start
+    }
+
+    // every row of the screen as it should look, starting with the prompt
+    fn frame(&self) -> Vec<String> {
+        let mut rows = vec![String::new(); (self.size.1 as usize).max(1)];
+
+        let columns = (self.size.0 as usize).saturating_sub(width::width(PROMPT));
+
+        write!(rows[0], "{style}{prompt}{query}{reset}",
+            style = self.theme.prompt,
+            prompt = width::truncate(PROMPT, self.size.0 as usize),
+            query = width::truncate(&self.query_string[self.prompt_start()..], columns),
+            reset = self.theme.prompt.reset()).ok();
+
+        if rows.len() > 1 {
+            rows[1] = width::truncate(&self.status_line(), self.results_width()).to_owned();
+        }
+
+        // leave room for the gutter in front of each result
+        let width = self.results_width().saturating_sub(2);
+        let gutter = self.theme.gutter;
+
+        let results = self.pre_selected.iter().map(|result| (result, false))
+            .chain(self.selected.iter().map(|result| (result, true)))
+            .chain(self.post_selected.iter().map(|result| (result, false)));
+
+        for ((result, selected), row) in results.zip(rows.iter_mut().skip(2)) {
+            let mark = match self.marked.contains(&result.path) {
+                true => format!("{}*{}", self.theme.marked, self.theme.marked.reset()),
+                false => String::from(" ")
+            };
+
+            if selected {
+                write!(row, "{}>{}{}", self.theme.selected, mark, self.theme.selected).ok();
+                write_result(row, result, &self.theme, self.theme.selected, width);
+                write!(row, "{}", self.theme.selected.reset()).ok();
+            } else {
+                write!(row, "{} {}{}", gutter, gutter.reset(), mark).ok();
+                write_result(row, result, &self.theme, Style::default(), width);
+            }
+        }
+
+        self.write_preview(&mut rows);
+
+        rows
+    }
+
+    // `⠋ 42/18231  fuzzy  3 marked  unable to run ...`
+    fn status_line(&self) -> String {
+        let spinner = match self.is_busy() {
+            true => SPINNER[self.status.spinner],
+            false => ' '
+        };
+
+        let mut line = format!("{} {}/{}  {}", spinner, self.status.matches, self.status.total, self.status.mode);
+
+        if !self.marked.is_empty() {
+            line.push_str(&format!("  {} marked", self.marked.len()));
+        }
 
-        for result in &self.pre_selected {
-            write!(f, "{} {} ", color::Bg(color::Black), color::Bg(color::Reset))?;
-            write_result(f, result, style::NoUnderline)?;
+        if let Some(ref error) = self.status.error {
+            line.push_str(&format!("  {}", error));
         }
 
-        for result in self.selected.as_ref() {
-            write!(f, "{background}{bold}>{reset}{background} ",
-                background = color::Bg(color::Black),
-                bold = style::Bold,
-                reset = style::Reset)?;
+        line
+    }
+
+    fn write_preview(&self, rows: &mut Vec<String>) {
+        let position = match self.preview_position {
+            Some(position) => position,
+            None => return
+        };
+
+        let (left, width) = match position {
+            Position::Right => (self.results_width() + 1, (self.size.0 as usize).saturating_sub(self.results_width() + 2)),
+            Position::Bottom => (1, self.size.0 as usize)
+        };
 
-            write_result(f, result, color::Bg(color::Black))?;
+        match position {
+            Position::Right => {
+                // anything left over from a longer result has to go first
+                for (i, row) in rows.iter_mut().enumerate().skip(1) {
+                    write!(row, "{}{}│", clear::UntilNewline, cursor::Goto(left as u16, i as u16 + 1)).ok();
+                }
+            },
 
-            write!(f, "{reset}\r", reset = color::Bg(color::Reset))?;
+            Position::Bottom => {
+                let separator: String = ::std::iter::repeat('─').take(width).collect();
+                rows.get_mut(self.preview_top() - 2).map(|row| row.push_str(&separator));
+            }
         }
 
-        for result in &self.post_selected {
-            write!(f, "{} {} ", color::Bg(color::Black), color::Bg(color::Reset))?;
-            write_result(f, result, style::NoUnderline)?;
+        let left = match position {
+            Position::Right => left + 2,
+            Position::Bottom => left
+        };
+
+        let lines = match self.preview {
+            Some(ref preview) if self.selected.is_some() => preview.visible_lines(self.preview_height()),
+            _ => return
+        };
+
+        for (i, line) in lines.iter().enumerate() {
+            let row = self.preview_top() + i;
+
+            rows.get_mut(row - 1).map(|drawn| {
+                write!(drawn, "{}{}", cursor::Goto(left as u16, row as u16), width::truncate(line, width)).ok();
+            });
         }
+    }
+}
 
-        let cursor_position = self.query_string.len() + PROMPT.len() + 1;
+// characters are grouped into clusters, so that combining marks and emoji
+// sequences are never split, and each is only drawn if it fits in the row
+fn clusters<T>(result: &QueryResult<T>) -> Vec<(String, bool)> {
+    let mut clusters: Vec<(String, bool)> = Vec::new();
+
+    for (i, c) in result.visible_chars() {
+        let highlighted = result.is_highlighted(i);
+
+        match clusters.last_mut() {
+            Some(&mut (ref mut cluster, ref mut cluster_highlighted)) if width::extends(cluster, c) => {
+                cluster.push(c);
+                *cluster_highlighted = *cluster_highlighted || highlighted;
+                continue;
+            },
+            _ => ()
+        }
 
-        write!(f, "{}", cursor::Goto(cursor_position as u16, 1))
+        clusters.push((c.to_string(), highlighted));
     }
+
+    clusters
 }
 
-fn write_result<S: fmt::Display>(f: &mut fmt::Formatter, result: &QueryResult, style: S) -> fmt::Result {
-    for (i, c) in result.path.char_indices() {
-        if result.positions.contains(&i) {
-            write!(f, "{bold}{c}{reset}{style}",
-                bold = style::Bold,
-                c = c,
-                reset = style::Reset,
-                style = style
-            )?;
+// matched characters are drawn in their own style, after which the style of
+// the row has to be restored. Rows that are too long lose their middle
+fn write_result<T>(f: &mut String, result: &QueryResult<T>, theme: &Theme, row: Style, columns: usize) {
+    let clusters = clusters(result);
+
+    let widths: Vec<usize> = clusters.iter().map(|&(ref cluster, _)| width::grapheme_width(cluster)).collect();
+    let highlighted: Vec<bool> = clusters.iter().map(|&(_, highlighted)| highlighted).collect();
+    let tail = clusters.iter().rposition(|&(ref cluster, _)| cluster == "/").map(|i| i + 1).unwrap_or(0);
+
+    let kept = match columns {
+        0 => vec![false; clusters.len()],
+        _ => width::elide(&widths, &highlighted, tail, columns)
+    };
+
+    for (i, (cluster, highlighted)) in clusters.into_iter().enumerate() {
+        if !kept[i] {
+            if columns > 0 && (i == 0 || kept[i - 1]) {
+                f.push_str(width::ELLIPSIS);
+            }
+
+            continue;
+        }
+
+        if highlighted {
+            write!(f, "{matched}{cluster}{reset}{row}",
+                matched = theme.matched,
+                cluster = cluster,
+                reset = theme.matched.reset(),
+                row = row
+            ).ok();
         } else {
-            write!(f, "{}", c)?;
+            f.push_str(&cluster);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use action::Action;
+    use index::Index;
+    use query::Query;
+
+    fn show<T: Clone>(screen: &mut Screen<T>, query: &Query<T>) {
+        let rows = screen.result_rows();
+
+        screen.set_query_string(&query.query_string());
+        screen.show(Outcome::of(query, rows, 0));
+    }
+
+    fn drawn<T: Clone>(screen: &mut Screen<T>) -> String {
+        let mut drawn = Vec::new();
+        screen.draw(&mut drawn).unwrap();
+        String::from_utf8(drawn).unwrap()
+    }
+
+    #[test]
+    fn only_changed_rows_are_drawn() {
+        let mut index = Index::new();
+        index.push("src/main.rs");
+        index.push("src/lib.rs");
+
+        let mut query = index.query();
+        let mut screen = Screen::new();
+        screen.set_size((40, 5));
+        show(&mut screen, &query);
+
+        let mut first = Vec::new();
+        screen.draw(&mut first).unwrap();
+        let first = String::from_utf8(first).unwrap();
+
+        assert!(first.starts_with(&clear::All.to_string()));
+        assert!(first.contains("main.rs"));
+
+        let mut unchanged = Vec::new();
+        screen.draw(&mut unchanged).unwrap();
+
+        assert_eq!(screen.cursor().to_string().into_bytes(), unchanged);
+
+        query.advance('l');
+        show(&mut screen, &query);
+
+        let mut changed = Vec::new();
+        screen.draw(&mut changed).unwrap();
+        let changed = String::from_utf8(changed).unwrap();
+
+        assert!(!changed.contains(&clear::All.to_string()));
+        assert!(changed.starts_with(&cursor::Goto(1, 1).to_string()));
+        assert!(changed.contains(">>> l"));
+    }
+
+    #[test]
+    fn long_queries_scroll_on_the_prompt() {
+        let mut index = Index::new();
+        index.push("src/main.rs");
+
+        let mut query = index.query();
+        query.set_query_string("abcdefghijklmnopqrstuvwxyz");
+
+        let mut screen = Screen::new();
+        screen.set_theme(Theme::none());
+        screen.set_size((14, 5));
+        show(&mut screen, &query);
+        screen.set_prompt_cursor(26);
+
+        // the prompt takes four columns, which leaves nine for the query
+        // and one for the cursor after it
+        let frame = drawn(&mut screen);
+
+        assert!(frame.contains(">>> rstuvwxyz"));
+        assert!(!frame.contains('q'));
+        assert_eq!(cursor::Goto(14, 1).to_string(), screen.cursor().to_string());
+
+        screen.click_prompt(5);
+        assert_eq!(17, screen.prompt_cursor());
+
+        screen.set_prompt_cursor(0);
+
+        assert!(drawn(&mut screen).contains(">>> abcdefghij"));
+        assert_eq!(cursor::Goto(5, 1).to_string(), screen.cursor().to_string());
+    }
+
+    #[test]
+    fn status_line() {
+        let mut index = Index::new();
+        index.push("src/main.rs");
+        index.push("src/lib.rs");
+        index.push("README.md");
+
+        let mut query = index.query();
+        query.advance('s');
+
+        let mut screen = Screen::new();
+        show(&mut screen, &query);
+
+        assert_eq!("  2/3  fuzzy", screen.status_line());
+
+        screen.toggle_mark();
+        screen.set_loading(true);
+
+        assert_eq!(format!("{} 2/3  fuzzy  1 marked", SPINNER[0]), screen.status_line());
+        assert_eq!(1, screen.accepted().len());
+
+        screen.set_loading(false);
+        screen.set_error(Some(String::from("unable to run find: not found")));
+
+        assert_eq!("  2/3  fuzzy  1 marked  unable to run find: not found", screen.status_line());
+
+        screen.set_error(None);
+
+        assert_eq!("  2/3  fuzzy  1 marked", screen.status_line());
+    }
+
+    #[test]
+    fn resizing_keeps_the_selection() {
+        let mut index = Index::new();
+
+        for path in &["a/1.rs", "a/2.rs", "a/3.rs", "a/4.rs", "a/5.rs"] {
+            index.push(path);
         }
+
+        let mut query = index.query();
+        query.advance('a');
+
+        let mut screen = Screen::new();
+        screen.set_size((40, 7));
+        show(&mut screen, &query);
+
+        screen.move_selection_down();
+        screen.move_selection_down();
+        screen.toggle_mark();
+
+        assert_eq!(3, screen.pre_selected.len());
+
+        // two rows of results are too few for the selection, so it is the
+        // last of them that is selected
+        screen.set_size((40, 4));
+        show(&mut screen, &query);
+
+        assert_eq!(1, screen.pre_selected.len());
+        assert!(screen.post_selected.is_empty());
+
+        screen.set_size((40, 7));
+        show(&mut screen, &query);
+
+        assert_eq!(1, screen.pre_selected.len());
+        assert_eq!(1, screen.accepted().len());
+
+        // a different query starts again from the top
+        query.advance('/');
+        show(&mut screen, &query);
+
+        assert!(screen.pre_selected.is_empty());
+    }
+
+    #[test]
+    fn commands_use_marked_paths() {
+        let mut index = Index::new();
+        index.push("src/main.rs");
+        index.push("src/lib.rs");
+        index.push("README.md");
+
+        let mut query = index.query();
+        query.advance('s');
+
+        let mut screen = Screen::new();
+        show(&mut screen, &query);
+
+        let action = Action::Execute(String::from("wc {}"));
+        let selected = screen.accepted();
+        assert_eq!(Some(format!("wc '{}'", selected[0])), action.command(&selected));
+
+        screen.toggle_mark();
+        screen.toggle_mark();
+
+        let marked = screen.accepted();
+        assert_eq!(2, marked.len());
+        assert_eq!(Some(format!("wc '{}' '{}'", marked[0], marked[1])), action.command(&marked));
     }
 
-    write!(f, "\n\r")
-}
\ No newline at end of file
+    #[test]
+    fn clicks() {
+        let mut index = Index::new();
+        index.push("src/main.rs");
+        index.push("src/lib.rs");
+        index.push("README.md");
+
+        let mut query = index.query();
+        query.set_query_string("漢s");
+
+        let mut screen = Screen::new();
+        show(&mut screen, &query);
+
+        // the prompt takes four columns, and the first character two more
+        screen.click_prompt(6);
+        assert_eq!(0, screen.prompt_cursor());
+
+        screen.click_prompt(7);
+        assert_eq!(1, screen.prompt_cursor());
+
+        screen.click_prompt(1);
+        assert_eq!(0, screen.prompt_cursor());
+
+        screen.click_prompt(40);
+        assert_eq!(2, screen.prompt_cursor());
+
+        query.set_query_string("s");
+        show(&mut screen, &query);
+
+        assert_eq!(None, screen.result_at(2));
+        assert_eq!(Some(1), screen.result_at(4));
+        assert_eq!(None, screen.result_at(5));
+
+        screen.select(1);
+        let second = screen.selected.as_ref().map(|result| result.path.clone());
+
+        screen.select(0);
+        assert!(screen.selected.as_ref().map(|result| result.path.clone()) != second);
+    }
+}
@@ -0,0 +1,225 @@
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+pub static ELLIPSIS: &'static str = "…";
+
+// whether a character belongs to the cluster before it rather than
+// starting a new one
+pub fn extends(cluster: &str, c: char) -> bool {
+    if cluster.is_empty() {
+        return false;
+    }
+
+    let mut extended = String::with_capacity(cluster.len() + c.len_utf8());
+    extended.push_str(cluster);
+    extended.push(c);
+
+    extended.graphemes(true).count() == 1
+}
+
+pub fn graphemes(s: &str) -> Vec<&str> {
+    s.graphemes(true).collect()
+}
+
+// a cluster is drawn as a single glyph, so a sequence of emoji joined
+// together is no wider than one of them
+pub fn grapheme_width(grapheme: &str) -> usize {
+    grapheme.width().min(2)
+}
+
+pub fn width(s: &str) -> usize {
+    graphemes(s).into_iter().map(grapheme_width).sum()
+}
+
+// the longest start of the string that fits in the given number of columns
+pub fn truncate(s: &str, columns: usize) -> &str {
+    let mut used = 0;
+    let mut end = 0;
+
+    for grapheme in graphemes(s) {
+        used += grapheme_width(grapheme);
+
+        if used > columns {
+            break;
+        }
+
+        end += grapheme.len();
+    }
+
+    &s[..end]
+}
+
+// which clusters of a row to keep so that it fits in the given columns, each
+// run of dropped clusters is shown as an ellipsis. Highlighted clusters are
+// kept first, then the ones from `tail` onwards, usually the file name, and
+// then the row is filled from both ends so that it is the middle that goes
+pub fn elide(widths: &[usize], highlighted: &[bool], tail: usize, columns: usize) -> Vec<bool> {
+    if widths.iter().sum::<usize>() <= columns {
+        return vec![true; widths.len()];
+    }
+
+    let mut kept = highlighted.to_vec();
+
+    // with too many matches to show them all the last ones are kept
+    while elided_width(widths, &kept) > columns {
+        match kept.iter().position(|&keep| keep) {
+            Some(i) => kept[i] = false,
+            None => break
+        }
+    }
+
+    let mut back = widths.len();
+
+    while back > tail && keep(widths, &mut kept, back - 1, columns) {
+        back -= 1;
+    }
+
+    let mut front = 0;
+    let (mut front_open, mut back_open) = (true, true);
+
+    while front < back && (front_open || back_open) {
+        if front_open {
+            front_open = keep(widths, &mut kept, front, columns);
+
+            if front_open {
+                front += 1;
+            }
+        }
+
+        if back_open && front < back {
+            back_open = keep(widths, &mut kept, back - 1, columns);
+
+            if back_open {
+                back -= 1;
+            }
+        }
+    }
+
+    kept
+}
+
+fn keep(widths: &[usize], kept: &mut Vec<bool>, i: usize, columns: usize) -> bool {
+    if kept[i] {
+        return true;
+    }
+
+    kept[i] = true;
+
+    if elided_width(widths, kept) > columns {
+        kept[i] = false;
+        return false;
+    }
+
+    true
+}
+
+fn elided_width(widths: &[usize], kept: &[bool]) -> usize {
+    let mut total = 0;
+
+    for (i, &width) in widths.iter().enumerate() {
+        if kept[i] {
+            total += width;
+        } else if i == 0 || kept[i - 1] {
+            total += 1;
+        }
+    }
+
+    total
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn character_widths() {
+        assert_eq!(1, grapheme_width("a"));
+        assert_eq!(2, grapheme_width("漢"));
+        assert_eq!(2, grapheme_width("Ａ"));
+        assert_eq!(1, grapheme_width("ｱ"));
+        assert_eq!(2, grapheme_width("😀"));
+        assert_eq!(0, grapheme_width("\u{301}"));
+        assert_eq!(1, grapheme_width("\u{1F93B}"));
+        assert_eq!(1, grapheme_width("\u{1F946}"));
+        assert_eq!(2, grapheme_width("\u{1F947}"));
+    }
+
+    #[test]
+    fn clusters() {
+        assert_eq!(vec!["e\u{301}", "a"], graphemes("e\u{301}a"));
+        assert_eq!(vec!["👩\u{200D}💻", "x"], graphemes("👩\u{200D}💻x"));
+        assert_eq!(vec!["🇬🇧", "🇫🇷"], graphemes("🇬🇧🇫🇷"));
+        assert_eq!(vec!["\u{1100}\u{1161}\u{11A8}", "a"], graphemes("\u{1100}\u{1161}\u{11A8}a"));
+    }
+
+    #[test]
+    fn extending_clusters() {
+        assert!(extends("e", '\u{301}'));
+        assert!(extends("👩\u{200D}", '💻'));
+        assert!(extends("🇬", '🇧'));
+        assert!(!extends("🇬🇧", '🇫'));
+        assert!(!extends("", '\u{301}'));
+        assert!(!extends("a", 'b'));
+    }
+
+    #[test]
+    fn string_widths() {
+        assert_eq!(8, width("src/漢字"));
+        assert_eq!(4, width("cafe\u{301}"));
+        assert_eq!(2, width("👩\u{200D}💻"));
+        assert_eq!(2, width("❤\u{FE0F}"));
+        assert_eq!(4, width("🇬🇧🇫🇷"));
+    }
+
+    #[test]
+    fn truncation() {
+        assert_eq!("ab漢", truncate("ab漢字", 5));
+        assert_eq!("ab", truncate("ab漢字", 3));
+        assert_eq!("cafe\u{301}", truncate("cafe\u{301}s", 4));
+    }
+
+    fn elided(s: &str, matched: &[usize], columns: usize) -> String {
+        let chars: Vec<char> = s.chars().collect();
+        let widths: Vec<usize> = chars.iter().map(|&c| grapheme_width(&c.to_string())).collect();
+        let highlighted: Vec<bool> = (0..chars.len()).map(|i| matched.contains(&i)).collect();
+        let tail = s.rfind('/').map(|i| s[..i].chars().count() + 1).unwrap_or(0);
+
+        let kept = elide(&widths, &highlighted, tail, columns);
+        let mut elided = String::new();
+
+        for (i, &c) in chars.iter().enumerate() {
+            if kept[i] {
+                elided.push(c);
+            } else if i == 0 || kept[i - 1] {
+                elided.push_str(ELLIPSIS);
+            }
+        }
+
+        elided
+    }
+
+    #[test]
+    fn short_rows_are_kept() {
+        assert_eq!("src/main.rs", elided("src/main.rs", &[], 20));
+    }
+
+    #[test]
+    fn middle_is_elided() {
+        assert_eq!("src…ep/file.rs", elided("src/a/b/c/deep/file.rs", &[], 14));
+    }
+
+    #[test]
+    fn matches_stay_visible() {
+        // the `d` of deep is matched, so it has to survive the elision
+        let elided = elided("src/a/b/c/deep/file.rs", &[10, 15], 14);
+
+        assert_eq!(14, width(&elided));
+        assert!(elided.contains('d'));
+        assert!(elided.ends_with("file.rs"));
+    }
+
+    #[test]
+    fn long_file_names_lose_their_start() {
+        assert_eq!("…_long_name.rs", elided("a_very_long_name.rs", &[], 14));
+    }
+}
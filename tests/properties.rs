@@ -0,0 +1,316 @@
+// the search through the graph should find exactly the paths that a plain
+// check of the query syntax would, a subsequence for each fuzzy pattern and
+// a substring for the rest, so random sets of paths and queries are run
+// through both. The generator has a fixed seed, a failure prints the paths
+// and query that caused it along with the seed for the case, which can be
+// run on its own with
+//
+//     FF_PROPERTY_SEED=<seed> cargo test --test properties
+
+extern crate ff;
+
+use std::collections::HashSet;
+use std::env;
+
+use ff::index::Index;
+use ff::query::Query;
+
+static CASES: usize = 300;
+
+// a small alphabet, so that most queries match something and most paths
+// share prefixes, with word boundaries, both cases and every operator
+static ALPHABET: &'static [char] = &['a', 'b', 'c', 'A', 'B', '/', '_', '-', '.', '$', '^', '!', '\'', '|', ' '];
+
+// a linear congruential generator, good enough for picking characters
+struct Random(u64);
+
+impl Random {
+    fn next(&mut self, n: usize) -> usize {
+        self.0 = self.0.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        ((self.0 >> 33) as usize) % n
+    }
+
+    fn string(&mut self, min: usize, max: usize) -> String {
+        let length = min + self.next(max - min + 1);
+        (0..length).map(|_| ALPHABET[self.next(ALPHABET.len())]).collect()
+    }
+}
+
+struct Case {
+    seed: u64,
+    paths: Vec<String>,
+    query_string: String
+}
+
+impl Case {
+    fn generate(seed: u64) -> Case {
+        let mut random = Random(seed);
+        let count = 1 + random.next(30);
+
+        let mut seen = HashSet::new();
+        let paths = (0..count)
+            .map(|_| random.string(1, 16))
+            .filter(|path| seen.insert(path.clone()))
+            .collect();
+
+        Case {
+            seed: seed,
+            paths: paths,
+            query_string: random.string(1, 4)
+        }
+    }
+
+    fn index(&self) -> Index {
+        let mut index = Index::new();
+
+        for path in &self.paths {
+            index.push(path);
+        }
+
+        index
+    }
+
+    fn fail(&self, message: &str) -> ! {
+        panic!("{}\nseed {}, query {:?}, paths {:?}", message, self.seed, self.query_string, self.paths);
+    }
+}
+
+// smart case, a pattern in lowercase matches either case
+fn same_character(query: char, path: char, case_sensitive: bool) -> bool {
+    match case_sensitive {
+        true => query == path,
+        false => query.to_lowercase().eq(path.to_lowercase())
+    }
+}
+
+// a space separated token of the query with its operators, parsed the
+// same way as the query syntax says, operators on their own are literal
+struct Pattern {
+    text: Vec<char>,
+    negated: bool,
+    exact: bool,
+    anchored_start: bool,
+    anchored_end: bool
+}
+
+impl Pattern {
+    fn parse(s: &str) -> Pattern {
+        let mut text = s;
+        let mut pattern = Pattern { text: Vec::new(), negated: false, exact: false, anchored_start: false, anchored_end: false };
+
+        if text.len() > 1 && text.starts_with('!') {
+            pattern.negated = true;
+            text = &text[1..];
+        }
+
+        if text.len() > 1 && text.starts_with('\'') {
+            pattern.exact = true;
+            text = &text[1..];
+        } else if text.len() > 1 && text.starts_with('^') {
+            pattern.anchored_start = true;
+            text = &text[1..];
+        }
+
+        if text.len() > 1 && text.ends_with('$') {
+            pattern.anchored_end = true;
+            text = &text[..text.len() - 1];
+        }
+
+        pattern.text = text.chars().collect();
+        pattern
+    }
+
+    fn is_contiguous(&self) -> bool {
+        self.exact || self.negated || self.anchored_start || self.anchored_end
+    }
+
+    fn case_sensitive(&self) -> bool {
+        self.text.iter().any(|c| c.is_uppercase())
+    }
+
+    fn found_in(&self, path: &[char]) -> bool {
+        let sensitive = self.case_sensitive();
+
+        if !self.is_contiguous() {
+            let mut path = path.iter();
+            return self.text.iter().all(|&q| path.any(|&p| same_character(q, p, sensitive)));
+        }
+
+        if self.text.len() > path.len() {
+            return false;
+        }
+
+        (0..path.len() - self.text.len() + 1)
+            .filter(|&start| !self.anchored_start || start == 0)
+            .filter(|&start| !self.anchored_end || start + self.text.len() == path.len())
+            .any(|start| self.text.iter().zip(&path[start..]).all(|(&q, &p)| same_character(q, p, sensitive)))
+    }
+
+    fn matches(&self, path: &[char]) -> bool {
+        self.found_in(path) != self.negated
+    }
+}
+
+// every clause has to match, a clause is patterns separated by `|` and only
+// one of them has to
+fn clauses(query_string: &str) -> Vec<Vec<Pattern>> {
+    let mut clauses: Vec<Vec<Pattern>> = Vec::new();
+    let mut or_next = false;
+
+    for s in query_string.split(' ').filter(|s| !s.is_empty()) {
+        if s == "|" {
+            or_next = !clauses.is_empty();
+            continue;
+        }
+
+        let pattern = Pattern::parse(s);
+
+        match clauses.last_mut() {
+            Some(clause) if or_next => clause.push(pattern),
+            _ => clauses.push(vec![pattern])
+        }
+
+        or_next = false;
+    }
+
+    clauses
+}
+
+fn should_match(query_string: &str, path: &str) -> bool {
+    let path: Vec<char> = path.chars().collect();
+
+    clauses(query_string).iter().all(|clause| clause.iter().any(|pattern| pattern.matches(&path)))
+}
+
+// each match as its path, score and positions in the order they were
+// matched, sorted by path
+fn outcome(query: &Query) -> Vec<(String, usize, Vec<usize>)> {
+    let mut outcome: Vec<(String, usize, Vec<usize>)> = query.matches().into_iter()
+        .map(|m| (m.path, m.score, m.positions))
+        .collect();
+
+    outcome.sort();
+    outcome
+}
+
+fn seeds() -> Vec<u64> {
+    match env::var("FF_PROPERTY_SEED").ok().and_then(|seed| seed.parse().ok()) {
+        Some(seed) => vec![seed],
+        None => (0..CASES as u64).collect()
+    }
+}
+
+#[test]
+fn results_match_the_query() {
+    for seed in seeds() {
+        let case = Case::generate(seed);
+        let index = case.index();
+        let mut query = index.query();
+        query.set_query_string(&case.query_string);
+
+        for result in query.results() {
+            if !should_match(&case.query_string, &result.path) {
+                case.fail(&format!("{} was returned, but doesn't match the query", result.path));
+            }
+        }
+    }
+}
+
+#[test]
+fn every_match_is_found() {
+    for seed in seeds() {
+        let case = Case::generate(seed);
+        let index = case.index();
+        let mut query = index.query();
+        query.set_query_string(&case.query_string);
+
+        let found: HashSet<String> = query.results().map(|result| result.path).collect();
+
+        for path in &case.paths {
+            if should_match(&case.query_string, path) && !found.contains(path) {
+                case.fail(&format!("{} matches the query, but wasn't returned", path));
+            }
+        }
+    }
+}
+
+#[test]
+fn positions_spell_out_the_query() {
+    for seed in seeds() {
+        let case = Case::generate(seed);
+        let index = case.index();
+        let mut query = index.query();
+        query.set_query_string(&case.query_string);
+
+        let clauses = clauses(&case.query_string);
+        let characters: Vec<char> = case.query_string.chars().collect();
+
+        for (path, _, positions) in outcome(&query) {
+            if !positions.windows(2).all(|w| w[0] < w[1]) {
+                case.fail(&format!("{} has positions {:?}, which aren't increasing", path, positions));
+            }
+
+            let matched: Vec<char> = positions.iter()
+                .map(|&position| path[position..].chars().next().unwrap_or_else(|| {
+                    case.fail(&format!("{} has position {}, past the end", path, position))
+                }))
+                .collect();
+
+            // with one plain pattern the positions spell it out exactly,
+            // otherwise they can only be characters from the query
+            match (clauses.len(), clauses.first().map(|clause| clause.len())) {
+                (1, Some(1)) if !clauses[0][0].negated => {
+                    let pattern = &clauses[0][0];
+
+                    if matched.len() != pattern.text.len() ||
+                        !pattern.text.iter().zip(&matched).all(|(&q, &p)| same_character(q, p, pattern.case_sensitive())) {
+                        case.fail(&format!("{} has positions {:?}, which spell {:?}", path, positions, matched));
+                    }
+                },
+                _ => if !matched.iter().all(|&p| characters.iter().any(|&q| same_character(q, p, false))) {
+                    case.fail(&format!("{} has positions {:?}, which aren't from the query", path, positions));
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn back_restores_results() {
+    for seed in seeds() {
+        let case = Case::generate(seed);
+        let index = case.index();
+        let mut query = index.query();
+        query.set_query_string(&case.query_string);
+
+        let before = outcome(&query);
+
+        // a couple more characters, which may well change the case
+        // sensitivity, and then back to where it was
+        let mut random = Random(seed);
+        let extra = random.string(1, 2);
+
+        for c in extra.chars() {
+            query.advance(c);
+        }
+
+        for _ in extra.chars() {
+            query.back();
+        }
+
+        if query.query_string() != case.query_string {
+            case.fail(&format!("backing out of {:?} left the query at {:?}", extra, query.query_string()));
+        }
+
+        if outcome(&query) != before {
+            case.fail(&format!("backing out of {:?} changed the results", extra));
+        }
+
+        let mut fresh = index.query();
+        fresh.set_query_string(&case.query_string);
+
+        if outcome(&fresh) != before {
+            case.fail("a fresh query found something else");
+        }
+    }
+}
@@ -0,0 +1,136 @@
+// the best few results for queries we rely on, kept in
+// tests/fixtures/ranking.txt, so that a change to how paths are scored shows
+// up as a change to what comes first. When a change to the ranking is
+// intended the expectations can be written again from the current results:
+//
+//     FF_UPDATE_RANKING=1 cargo test --test ranking
+//
+// and the difference reviewed along with the change
+
+extern crate ff;
+
+use std::env;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::PathBuf;
+
+use ff::index::Index;
+
+// how many results are checked for each query
+static TOP: usize = 5;
+
+fn fixture_path() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests").join("fixtures").join("ranking.txt")
+}
+
+// the best results in order, paths with the same score are in alphabetical
+// order so that ties always come out the same way
+fn ranked(index: &Index, query_string: &str) -> Vec<String> {
+    let mut query = index.query();
+    query.set_query_string(query_string);
+
+    let mut results: Vec<(usize, String)> = query.results().map(|result| (result.score, result.path)).collect();
+    results.sort();
+
+    results.into_iter().take(TOP).map(|(_, path)| path).collect()
+}
+
+// `paths` starts a new set of paths, `query <query string>` what is
+// expected when searching the set above, with each path or result on an
+// indented line of its own. Blank lines and lines starting with a `#` are
+// left alone
+enum Line<'a> {
+    Paths,
+    Query(&'a str),
+    Item(&'a str),
+    Other
+}
+
+fn parse_line<'a>(line: &'a str) -> Line<'a> {
+    if line.starts_with(' ') && !line.trim().is_empty() {
+        Line::Item(line.trim())
+    } else if line == "paths" {
+        Line::Paths
+    } else if line.starts_with("query ") {
+        Line::Query(&line["query ".len()..])
+    } else {
+        Line::Other
+    }
+}
+
+struct Check {
+    query_string: String,
+    expected: Vec<String>,
+    actual: Vec<String>
+}
+
+// every query in the fixture, along with the lines of the fixture as they
+// would be with the current results
+fn check_fixture(fixture: &str) -> (Vec<Check>, String) {
+    let mut index = Index::new();
+    let mut in_paths = false;
+    let mut checks: Vec<Check> = Vec::new();
+    let mut updated = String::new();
+
+    for line in fixture.lines() {
+        match parse_line(line) {
+            Line::Paths => {
+                index = Index::new();
+                in_paths = true;
+            },
+            Line::Query(query_string) => {
+                in_paths = false;
+
+                let actual = ranked(&index, query_string);
+
+                updated.push_str(line);
+                updated.push('\n');
+
+                for path in &actual {
+                    updated.push_str(&format!("    {}\n", path));
+                }
+
+                checks.push(Check { query_string: query_string.to_owned(), expected: Vec::new(), actual: actual });
+                continue;
+            },
+            Line::Item(item) if in_paths => index.push(item),
+            Line::Item(item) => {
+                checks.last_mut().map(|check| check.expected.push(item.to_owned()));
+                continue;
+            },
+            Line::Other => in_paths = false
+        }
+
+        updated.push_str(line);
+        updated.push('\n');
+    }
+
+    (checks, updated)
+}
+
+#[test]
+fn ranking() {
+    let mut fixture = String::new();
+    File::open(fixture_path())
+        .and_then(|mut file| file.read_to_string(&mut fixture))
+        .expect("read the ranking fixture");
+
+    let (checks, updated) = check_fixture(&fixture);
+
+    if env::var("FF_UPDATE_RANKING").is_ok() {
+        File::create(fixture_path())
+            .and_then(|mut file| file.write_all(updated.as_bytes()))
+            .expect("write the ranking fixture");
+
+        return;
+    }
+
+    let failures: Vec<String> = checks.iter()
+        .filter(|check| check.expected != check.actual)
+        .map(|check| format!("query {}\n  expected {:?}\n  got      {:?}", check.query_string, check.expected, check.actual))
+        .collect();
+
+    assert!(!checks.is_empty());
+    assert!(failures.is_empty(), "the ranking changed for {} of {} queries\n\n{}\n\nif this is intended, run FF_UPDATE_RANKING=1 cargo test --test ranking",
+        failures.len(), checks.len(), failures.join("\n\n"));
+}